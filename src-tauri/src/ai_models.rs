@@ -0,0 +1,273 @@
+use sha2::{Digest, Sha256};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tauri::{AppHandle, Emitter, Manager, Runtime, path::BaseDirectory};
+use tracing::{error, warn};
+
+use crate::db;
+use crate::error::{AppError, ErrorCode};
+
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// OCR models used by the engine
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelKind {
+    Detection,
+    Recognition,
+}
+
+impl ModelKind {
+    pub const ALL: [ModelKind; 2] = [ModelKind::Detection, ModelKind::Recognition];
+
+    fn default_file(&self) -> &'static str {
+        match self {
+            ModelKind::Detection => "text-detection.rten",
+            ModelKind::Recognition => "text-recognition.rten",
+        }
+    }
+}
+
+/// Where a model was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelSource {
+    /// The user model directory in AppData or the `model_dir` setting
+    Custom,
+    /// The models bundled with the application resources
+    Bundled,
+}
+
+/// Result of verifying a model file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelState {
+    /// Present and matching the manifest
+    Ok,
+    /// Present, but the manifest does not pin a checksum for it
+    Unverified,
+    Missing,
+    SizeMismatch,
+    ChecksumMismatch,
+}
+
+impl ModelState {
+    /// Whether the engine may load a model in this state
+    pub fn is_usable(&self) -> bool {
+        matches!(self, ModelState::Ok | ModelState::Unverified)
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ModelStatus {
+    pub kind: ModelKind,
+    pub file: String,
+    pub path: Option<String>,
+    pub source: Option<ModelSource>,
+    pub state: ModelState,
+    pub size: Option<u64>,
+    pub sha256: Option<String>,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+struct ManifestEntry {
+    kind: ModelKind,
+    file: String,
+    size: Option<u64>,
+    sha256: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+struct ModelManifest {
+    models: Vec<ManifestEntry>,
+}
+
+impl ModelManifest {
+    fn load(dir: &Path) -> Option<ModelManifest> {
+        let content = std::fs::read_to_string(dir.join(MANIFEST_FILE)).ok()?;
+        match serde_json::from_str(&content) {
+            Ok(manifest) => Some(manifest),
+            Err(e) => {
+                warn!("Invalid model manifest in {}: {}", dir.display(), e);
+                None
+            }
+        }
+    }
+
+    fn entry(&self, kind: ModelKind) -> Option<&ManifestEntry> {
+        self.models.iter().find(|entry| entry.kind == kind)
+    }
+}
+
+fn bundled_dir<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, Box<dyn Error + Send + Sync>> {
+    Ok(app.path().resolve("resources/ai_models", BaseDirectory::Resource)?)
+}
+
+/// The user model directory, from the `model_dir` setting or `AppData/ai_models`
+fn custom_dir<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, Box<dyn Error + Send + Sync>> {
+    match db::read_setting(app, "model_dir") {
+        Some(dir) if !dir.trim().is_empty() => Ok(PathBuf::from(dir.trim())),
+        _ => Ok(app.path().resolve("ai_models", BaseDirectory::AppData)?),
+    }
+}
+
+/// A model file found on disk, not verified yet
+struct LocatedModel {
+    path: PathBuf,
+    source: ModelSource,
+    entry: Option<ManifestEntry>,
+}
+
+/// Finds a model, preferring the custom directory over the bundled resources.
+/// Returns the expected file name with the model when one exists.
+fn locate_model<R: Runtime>(app: &AppHandle<R>, kind: ModelKind) -> Result<(String, Option<LocatedModel>), Box<dyn Error + Send + Sync>> {
+    let bundled = bundled_dir(app)?;
+    let bundled_manifest = ModelManifest::load(&bundled).unwrap_or_default();
+
+    let custom = custom_dir(app)?;
+    let custom_manifest = ModelManifest::load(&custom).unwrap_or_default();
+
+    let file = bundled_manifest
+        .entry(kind)
+        .map(|entry| entry.file.clone())
+        .unwrap_or_else(|| kind.default_file().to_string());
+
+    // Custom models are only checked against a manifest shipped alongside them
+    if let Some(custom_entry) = custom_manifest.entry(kind) {
+        let path = custom.join(&custom_entry.file);
+        if path.exists() {
+            return Ok((file, Some(LocatedModel { path, source: ModelSource::Custom, entry: Some(custom_entry.clone()) })));
+        }
+    }
+    let custom_path = custom.join(&file);
+    if custom_path.exists() {
+        return Ok((file, Some(LocatedModel { path: custom_path, source: ModelSource::Custom, entry: None })));
+    }
+
+    let bundled_path = bundled.join(&file);
+    if bundled_path.exists() {
+        let entry = bundled_manifest.entry(kind).cloned();
+        return Ok((file, Some(LocatedModel { path: bundled_path, source: ModelSource::Bundled, entry })));
+    }
+
+    Ok((file, None))
+}
+
+/// Verifies a model, preferring the custom directory over the bundled resources
+pub fn verify_model<R: Runtime>(app: &AppHandle<R>, kind: ModelKind) -> Result<ModelStatus, Box<dyn Error + Send + Sync>> {
+    match locate_model(app, kind)? {
+        (_, Some(model)) => verify_file(kind, &model.path, model.source, model.entry.as_ref()),
+        (file, None) => Ok(ModelStatus {
+            kind,
+            file,
+            path: None,
+            source: None,
+            state: ModelState::Missing,
+            size: None,
+            sha256: None,
+        }),
+    }
+}
+
+/// Identifies the file a model would be loaded from without hashing it, so the engine
+/// can notice a changed `model_dir` or a replaced model cheaply
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelStamp {
+    path: PathBuf,
+    size: u64,
+    modified: Option<SystemTime>,
+}
+
+/// The stamp of a model's current file, `None` when it is missing
+pub fn model_stamp<R: Runtime>(app: &AppHandle<R>, kind: ModelKind) -> Result<Option<ModelStamp>, Box<dyn Error + Send + Sync>> {
+    let Some(model) = locate_model(app, kind)?.1 else {
+        return Ok(None);
+    };
+    let metadata = std::fs::metadata(&model.path)?;
+    Ok(Some(ModelStamp { path: model.path, size: metadata.len(), modified: metadata.modified().ok() }))
+}
+
+fn verify_file(kind: ModelKind, path: &Path, source: ModelSource, entry: Option<&ManifestEntry>) -> Result<ModelStatus, Box<dyn Error + Send + Sync>> {
+    let size = std::fs::metadata(path)?.len();
+    let sha256 = file_sha256(path)?;
+
+    let expected_size = entry.and_then(|e| e.size);
+    let expected_sha256 = entry.and_then(|e| e.sha256.as_deref());
+
+    let state = if expected_size.is_some_and(|expected| expected != size) {
+        ModelState::SizeMismatch
+    } else if let Some(expected) = expected_sha256 {
+        if expected.eq_ignore_ascii_case(&sha256) {
+            ModelState::Ok
+        } else {
+            ModelState::ChecksumMismatch
+        }
+    } else {
+        ModelState::Unverified
+    };
+
+    Ok(ModelStatus {
+        kind,
+        file: path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default(),
+        path: Some(path.to_string_lossy().into_owned()),
+        source: Some(source),
+        state,
+        size: Some(size),
+        sha256: Some(sha256),
+    })
+}
+
+fn file_sha256(path: &Path) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Verifies every model
+pub fn verify_models<R: Runtime>(app: &AppHandle<R>) -> Result<Vec<ModelStatus>, Box<dyn Error + Send + Sync>> {
+    ModelKind::ALL.iter().map(|&kind| verify_model(app, kind)).collect()
+}
+
+/// Returns the path of a verified model, or an error describing why it cannot be used
+pub fn resolve_model<R: Runtime>(app: &AppHandle<R>, kind: ModelKind) -> Result<PathBuf, Box<dyn Error + Send + Sync>> {
+    let status = verify_model(app, kind)?;
+
+    match (&status.path, status.state.is_usable()) {
+        (Some(path), true) => Ok(PathBuf::from(path)),
+        (Some(path), false) => Err(AppError::new(
+            ErrorCode::ModelInvalid,
+            format!("{:?} model at {} failed verification: {:?}", kind, path, status.state),
+        ).into()),
+        (None, _) => Err(AppError::new(
+            ErrorCode::ModelMissing,
+            format!(
+                "{:?} model {} is missing, place it in {} or the bundled resources",
+                kind,
+                status.file,
+                custom_dir(app)?.display()
+            ),
+        ).into()),
+    }
+}
+
+/// Verifies the models in the background at startup and reports problems to the frontend
+pub fn check_on_startup(app: &AppHandle) {
+    let handle = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let task_handle = handle.clone();
+        match tokio::task::spawn_blocking(move || verify_models(&task_handle)).await {
+            Ok(Ok(statuses)) => {
+                for status in statuses.iter().filter(|s| !s.state.is_usable()) {
+                    error!("OCR model problem: {:?} {} is {:?}", status.kind, status.file, status.state);
+                }
+                if let Err(e) = handle.emit("model-status", &statuses) {
+                    warn!("Failed to emit model status event: {}", e);
+                }
+            }
+            Ok(Err(e)) => error!("Error verifying OCR models: {}", e),
+            Err(e) => error!("Model verification task failed: {}", e),
+        }
+    });
+}
@@ -0,0 +1,348 @@
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Path, Query, Request, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use lazy_static::lazy_static;
+use once_cell::sync::OnceCell;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use std::error::Error;
+use std::sync::Mutex;
+use tauri::{AppHandle, Listener};
+use tokio::sync::{broadcast, watch};
+use tracing::{error, info, warn};
+
+use crate::db::{self, DbConnection};
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::models::{screenshots, MatchFieldRecord};
+use crate::stats;
+
+/// Port used when `api_port` is unset or invalid
+const DEFAULT_PORT: u16 = 7373;
+
+/// Length of a generated token
+const TOKEN_LENGTH: usize = 32;
+
+/// Matches returned by `/api/matches` when no limit is given, and the most it returns
+const DEFAULT_LIMIT: i64 = 50;
+const MAX_LIMIT: i64 = 500;
+
+/// WebSocket route, the only one that accepts the token as a `token` query parameter
+const EVENTS_PATH: &str = "/api/events";
+
+/// Events buffered for slow WebSocket clients before they start missing some
+const EVENT_BUFFER: usize = 64;
+
+/// Frontend events also streamed to WebSocket clients
+const FORWARDED_EVENTS: [&str; 7] = [
+    "screenshot-status",
+    "screenshot-error",
+    "ocr-complete",
+    "fields-flagged",
+    "match-recognized",
+    "reprocess-progress",
+    "reprocess-complete",
+];
+
+lazy_static! {
+    /// Stops the running server and closes its WebSocket connections, `None` when the API is disabled
+    static ref SHUTDOWN: Mutex<Option<watch::Sender<bool>>> = Mutex::new(None);
+}
+
+/// Pipeline events fan out to every WebSocket client, set up once on first start
+static EVENTS: OnceCell<broadcast::Sender<ApiEvent>> = OnceCell::new();
+
+/// A pipeline event as sent to WebSocket clients
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ApiEvent {
+    pub event: String,
+    pub payload: serde_json::Value,
+}
+
+/// A recognized match with its fields
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct MatchSummary {
+    pub id: i32,
+    pub mission_type: String,
+    pub language: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub fields: Vec<MatchFieldRecord>,
+}
+
+#[derive(Clone)]
+struct ApiState {
+    app: AppHandle,
+    token: String,
+    events: broadcast::Sender<ApiEvent>,
+    stopped: watch::Receiver<bool>,
+}
+
+#[derive(serde::Deserialize)]
+struct MatchQuery {
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+#[derive(serde::Deserialize)]
+struct StatsQuery {
+    since: Option<String>,
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let status = match self.code {
+            ErrorCode::NotFound => StatusCode::NOT_FOUND,
+            ErrorCode::InvalidInput => StatusCode::BAD_REQUEST,
+            ErrorCode::Unauthorized => StatusCode::UNAUTHORIZED,
+            ErrorCode::DbLocked | ErrorCode::DbUnavailable | ErrorCode::Busy => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, Json(self)).into_response()
+    }
+}
+
+/// Starts the API server when `api_enabled` is set. It only listens on 127.0.0.1.
+pub async fn start(app: &AppHandle) {
+    if db::setting(app, "api_enabled").await.as_deref() != Some("true") {
+        return;
+    }
+
+    let port = db::setting(app, "api_port")
+        .await
+        .and_then(|v| v.trim().parse::<u16>().ok())
+        .unwrap_or(DEFAULT_PORT);
+
+    let (shutdown, stopped) = watch::channel(false);
+    if let Some(previous) = SHUTDOWN.lock().unwrap().replace(shutdown) {
+        let _ = previous.send(true);
+    }
+
+    let app = app.clone();
+    let events = events(&app).clone();
+    tauri::async_runtime::spawn(async move {
+        let token = match ensure_token(&app).await {
+            Ok(token) => token,
+            Err(e) => {
+                error!("Failed to set up the API token, the API stays off: {}", e);
+                return;
+            }
+        };
+
+        let state = ApiState { app, token, events, stopped };
+        if let Err(e) = serve(state, port).await {
+            error!("API server on port {} failed: {}", port, e);
+        }
+    });
+}
+
+/// Stops the server if it is running
+pub fn stop() {
+    if let Some(shutdown) = SHUTDOWN.lock().unwrap().take() {
+        let _ = shutdown.send(true);
+    }
+}
+
+/// Applies changed API settings by restarting the server
+pub async fn reload(app: &AppHandle) {
+    stop();
+    start(app).await;
+}
+
+/// The configured token, generating and saving one on first use.
+/// `api_token` is a secret setting, diagnostics bundles never include it and the settings view masks it.
+pub async fn ensure_token(app: &AppHandle) -> AppResult<String> {
+    db::run(app, |conn| {
+        if let Some(token) = db::get_setting(conn, "api_token").filter(|token| !token.trim().is_empty()) {
+            return Ok(token.trim().to_string());
+        }
+
+        let token: String = rand::thread_rng().sample_iter(&Alphanumeric).take(TOKEN_LENGTH).map(char::from).collect();
+        db::set_setting(conn, "api_token", &token)?;
+        info!("Generated a new API token");
+        Ok::<_, diesel::result::Error>(token)
+    })
+    .await
+}
+
+/// The event channel, forwarding frontend events into it the first time it is used
+pub(crate) fn events(app: &AppHandle) -> &'static broadcast::Sender<ApiEvent> {
+    EVENTS.get_or_init(|| {
+        let (sender, _) = broadcast::channel(EVENT_BUFFER);
+        for name in FORWARDED_EVENTS {
+            let sender = sender.clone();
+            app.listen_any(name, move |event| {
+                let payload = serde_json::from_str(event.payload()).unwrap_or(serde_json::Value::Null);
+                // Sending fails when no client is connected, the event is simply dropped
+                let _ = sender.send(ApiEvent { event: name.to_string(), payload });
+            });
+        }
+        sender
+    })
+}
+
+async fn serve(state: ApiState, port: u16) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut stopped = state.stopped.clone();
+    let router = Router::new()
+        .route("/api/matches", get(list_matches))
+        .route("/api/matches/latest", get(latest_match))
+        .route("/api/matches/:id", get(get_match))
+        .route("/api/stats", get(get_stats))
+        .route(EVENTS_PATH, get(stream_events))
+        .layer(middleware::from_fn_with_state(state.clone(), require_token))
+        .with_state(state);
+
+    let listener = tokio::net::TcpListener::bind(("127.0.0.1", port)).await?;
+    info!("API listening on http://127.0.0.1:{}", port);
+    axum::serve(listener, router)
+        // Graceful shutdown waits for open connections, `forward_events` ends its loop on the same signal
+        .with_graceful_shutdown(async move {
+            let _ = stopped.wait_for(|stopped| *stopped).await;
+        })
+        .await?;
+    info!("API on port {} stopped", port);
+    Ok(())
+}
+
+async fn require_token(State(state): State<ApiState>, request: Request, next: Next) -> Response {
+    // Browsers can't set headers on a WebSocket upgrade, every other route needs the header
+    // so the token doesn't end up in URLs that get logged
+    let query = if request.uri().path() == EVENTS_PATH { request.uri().query() } else { None };
+    if is_authorized(request.headers(), query, &state.token) {
+        next.run(request).await
+    } else {
+        AppError::new(ErrorCode::Unauthorized, "Missing or invalid API token").into_response()
+    }
+}
+
+/// Accepts `Authorization: Bearer <token>`, or a `token` query parameter when one is passed in.
+/// Only the WebSocket route passes its query, see `require_token`.
+fn is_authorized(headers: &HeaderMap, query: Option<&str>, token: &str) -> bool {
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(|value| value.trim().to_string());
+    let from_query = query.and_then(|query| {
+        query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, _)| *key == "token")
+            .and_then(|(_, value)| urlencoding::decode(value).ok())
+            .map(|value| value.into_owned())
+    });
+
+    !token.is_empty() && [bearer, from_query].into_iter().flatten().any(|given| given == token)
+}
+
+async fn list_matches(State(state): State<ApiState>, Query(query): Query<MatchQuery>) -> AppResult<Json<Vec<MatchSummary>>> {
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    let offset = query.offset.unwrap_or(0).max(0);
+    let matches = db::run(&state.app, move |conn| load_matches(conn, None, limit, offset))
+        .await
+        .map_err(|e| e.context("Failed to load matches"))?;
+    Ok(Json(matches))
+}
+
+async fn latest_match(State(state): State<ApiState>) -> AppResult<Json<MatchSummary>> {
+    db::run(&state.app, move |conn| load_matches(conn, None, 1, 0))
+        .await
+        .map_err(|e| e.context("Failed to load the latest match"))?
+        .pop()
+        .map(Json)
+        .ok_or_else(|| AppError::new(ErrorCode::NotFound, "No match recorded yet"))
+}
+
+async fn get_match(State(state): State<ApiState>, Path(id): Path<i32>) -> AppResult<Json<MatchSummary>> {
+    db::run(&state.app, move |conn| load_matches(conn, Some(id), 1, 0))
+        .await
+        .map_err(|e| e.context(format!("Failed to load match {}", id)))?
+        .pop()
+        .map(Json)
+        .ok_or_else(|| AppError::new(ErrorCode::NotFound, format!("No match with id {}", id)))
+}
+
+async fn get_stats(State(state): State<ApiState>, Query(query): Query<StatsQuery>) -> AppResult<Json<stats::MatchStats>> {
+    let since = stats::parse_since(query.since.as_deref())?;
+    let stats = db::run(&state.app, move |conn| stats::collect(conn, since))
+        .await
+        .map_err(|e| e.context("Failed to compute stats"))?;
+    Ok(Json(stats))
+}
+
+async fn stream_events(State(state): State<ApiState>, ws: WebSocketUpgrade) -> Response {
+    let receiver = state.events.subscribe();
+    let stopped = state.stopped.clone();
+    ws.on_upgrade(move |socket| forward_events(socket, receiver, stopped))
+}
+
+async fn forward_events(mut socket: WebSocket, mut receiver: broadcast::Receiver<ApiEvent>, mut stopped: watch::Receiver<bool>) {
+    loop {
+        tokio::select! {
+            _ = stopped.wait_for(|stopped| *stopped) => {
+                let _ = socket.send(Message::Close(None)).await;
+                break;
+            }
+            event = receiver.recv() => match event {
+                Ok(event) => {
+                    let Ok(text) = serde_json::to_string(&event) else { continue };
+                    if socket.send(Message::Text(text)).await.is_err() {
+                        break;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => warn!("WebSocket client missed {} events", skipped),
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                // Clients have nothing to send, pings are answered by axum
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+/// Recognized matches, newest first, with their fields
+fn load_matches(conn: &mut DbConnection, id: Option<i32>, limit: i64, offset: i64) -> Result<Vec<MatchSummary>, diesel::result::Error> {
+    let mut query = screenshots::table
+        .filter(screenshots::recognized.eq(true))
+        .select((screenshots::id, screenshots::mission_type, screenshots::manual_mission_type, screenshots::language, screenshots::created_at))
+        .order(screenshots::id.desc())
+        .limit(limit)
+        .offset(offset)
+        .into_boxed();
+    if let Some(id) = id {
+        query = query.filter(screenshots::id.eq(id));
+    }
+    let rows: Vec<(i32, String, Option<String>, Option<String>, NaiveDateTime)> = query.load(conn)?;
+
+    rows.into_iter()
+        .map(|(id, detected, manual, language, created_at)| {
+            let mission_type = manual.unwrap_or(detected);
+            Ok(MatchSummary { id, mission_type, language, created_at, fields: db::get_match_fields(conn, id)? })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn test_token_from_header_or_query() {
+        let mut headers = HeaderMap::new();
+        assert!(!is_authorized(&headers, None, "secret"));
+        assert!(is_authorized(&headers, Some("limit=5&token=secret"), "secret"));
+        assert!(!is_authorized(&headers, Some("token=wrong"), "secret"));
+
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_static("Bearer secret"));
+        assert!(is_authorized(&headers, None, "secret"));
+        // An empty configured token never matches
+        assert!(!is_authorized(&headers, Some("token="), ""));
+    }
+}
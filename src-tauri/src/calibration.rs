@@ -0,0 +1,307 @@
+use image::DynamicImage;
+use std::error::Error;
+use tauri::AppHandle;
+use tracing::{info, warn};
+
+use crate::crop::{self, CropRegion};
+use crate::db;
+use crate::debug::{self, REGION_COLOR, WORD_COLOR};
+use crate::error::{AppError, ErrorCode};
+use crate::labels::{self, GameLanguage, Label};
+use crate::localize::{self, Rect, RegionLayout};
+use crate::ocr::{self, OcrLine, OcrResult, TextRect};
+use crate::preprocess;
+
+/// Settings holding a calibration, followed by the `<width>x<height>` it was fitted for
+const SETTING_PREFIX: &str = "region_calibration_";
+
+/// Labels needed before a fit is trusted, a single label can't tell scale from offset
+const MIN_ANCHORS: usize = 2;
+
+/// Scale and offset mapping the 1920x1080 crop configs onto captures of one resolution
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Calibration {
+    pub width: u32,
+    pub height: u32,
+    pub scale_x: f32,
+    pub scale_y: f32,
+    pub offset_x: f32,
+    pub offset_y: f32,
+}
+
+impl Calibration {
+    /// The rectangle of a region in a capture of the calibrated resolution
+    pub fn rect(&self, region: CropRegion) -> Rect {
+        let (x, y, width, height) = crop::base_rect(region);
+        let width = ((width as f32 * self.scale_x).round() as u32).clamp(1, self.width);
+        let height = ((height as f32 * self.scale_y).round() as u32).clamp(1, self.height);
+        let x = (self.offset_x + x as f32 * self.scale_x).round().clamp(0.0, (self.width - width) as f32) as u32;
+        let y = (self.offset_y + y as f32 * self.scale_y).round().clamp(0.0, (self.height - height) as f32) as u32;
+        (x, y, width, height)
+    }
+
+    pub fn layout(&self) -> RegionLayout {
+        RegionLayout::from_rects(CropRegion::ALL.into_iter().map(|region| (region, self.rect(region))).collect())
+    }
+}
+
+/// A label found in the sample screenshot and the region it anchors
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct CalibrationAnchor {
+    pub region: CropRegion,
+    pub text: String,
+    pub rect: TextRect,
+}
+
+/// Saved calibration with the anchors it was fitted to and a preview for the user to confirm
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct CalibrationResult {
+    pub calibration: Calibration,
+    pub anchors: Vec<CalibrationAnchor>,
+    pub regions: Vec<(CropRegion, Rect)>,
+    /// Largest distance in pixels between a found label and where the fit puts it
+    pub max_error: f32,
+    /// The screenshot with the calibrated regions and found labels drawn on, as base64 PNG
+    pub preview: String,
+}
+
+fn setting_key(width: u32, height: u32) -> String {
+    format!("{}{}x{}", SETTING_PREFIX, width, height)
+}
+
+/// The calibration saved for a resolution, if any
+pub async fn load(app: &AppHandle, width: u32, height: u32) -> Option<Calibration> {
+    let key = setting_key(width, height);
+    parse(&key, &db::setting(app, &key).await?, width, height)
+}
+
+/// Every calibration among already loaded settings, for code that handles captures of any resolution
+pub fn saved(settings: &[(String, String)]) -> Vec<Calibration> {
+    settings
+        .iter()
+        .filter_map(|(key, json)| {
+            let (width, height) = key.strip_prefix(SETTING_PREFIX)?.split_once('x')?;
+            parse(key, json, width.parse().ok()?, height.parse().ok()?)
+        })
+        .collect()
+}
+
+fn parse(key: &str, json: &str, width: u32, height: u32) -> Option<Calibration> {
+    if json.trim().is_empty() {
+        return None;
+    }
+    match serde_json::from_str::<Calibration>(json) {
+        Ok(calibration) if calibration.width == width && calibration.height == height => Some(calibration),
+        Ok(_) => {
+            warn!("Ignoring {}, it was saved for another resolution", key);
+            None
+        }
+        Err(e) => {
+            warn!("Invalid {} setting, using fixed regions: {}", key, e);
+            None
+        }
+    }
+}
+
+/// Finds the expected labels in a mission summary screenshot, fits scale and offset of the
+/// crop configs to them and saves the result for the screenshot's resolution.
+/// `language` is the configured game language, `None` detects it from the heading.
+pub fn calibrate(app: &AppHandle, base64_image: &str, language: Option<GameLanguage>) -> Result<CalibrationResult, Box<dyn Error + Send + Sync>> {
+    let image = crop::decode_image(base64_image)?;
+    let (width, height) = (image.width(), image.height());
+    let result = ocr::recognize_image(app, &image)?;
+
+    let language = language
+        .or_else(|| labels::detect_language(&result.text()))
+        .ok_or_else(|| AppError::new(ErrorCode::InvalidInput, "No mission summary heading found, use a screenshot of the mission summary"))?;
+
+    let anchors = find_labels(width, &result, language);
+    if anchors.len() < MIN_ANCHORS {
+        return Err(AppError::new(
+            ErrorCode::InvalidInput,
+            format!("Found {} of the expected labels, at least {} are needed", anchors.len(), MIN_ANCHORS),
+        ).into());
+    }
+
+    let points: Vec<((f32, f32), (f32, f32))> = anchors
+        .iter()
+        .filter_map(|anchor| Some((base_anchor(anchor.region)?, (anchor.rect.x as f32, anchor.rect.y as f32))))
+        .collect();
+    let calibration = fit(width, height, &points)?;
+    let max_error = points
+        .iter()
+        .map(|&((base_x, base_y), (x, y))| {
+            let fitted_x = calibration.offset_x + base_x * calibration.scale_x;
+            let fitted_y = calibration.offset_y + base_y * calibration.scale_y;
+            ((fitted_x - x).powi(2) + (fitted_y - y).powi(2)).sqrt()
+        })
+        .fold(0.0, f32::max);
+
+    let mut conn = db::connection(app)?;
+    db::set_setting(&mut conn, &setting_key(width, height), &serde_json::to_string(&calibration)?)?;
+    info!("Saved region calibration for {}x{}: {:?}, max error {:.1}px", width, height, calibration, max_error);
+
+    let regions: Vec<(CropRegion, Rect)> = CropRegion::ALL.into_iter().map(|region| (region, calibration.rect(region))).collect();
+    let preview = preprocess::encode_png(&annotate(image, &regions, &anchors))?;
+
+    Ok(CalibrationResult { calibration, anchors, regions, max_error, preview })
+}
+
+/// Where a region's heading sits in the 1920x1080 layout
+fn base_anchor(region: CropRegion) -> Option<(f32, f32)> {
+    let (offset_x, offset_y) = localize::anchor_offset(region)?;
+    let (x, y, _, _) = crop::base_rect(region);
+    Some((x as f32 + offset_x, y as f32 + offset_y))
+}
+
+/// Finds the mission summary heading and the card headings.
+/// Cards are assigned by reading order, two rows of two, so any scale or offset is accepted.
+fn find_labels(width: u32, result: &OcrResult, language: GameLanguage) -> Vec<CalibrationAnchor> {
+    let anchor = |region: CropRegion, line: &OcrLine| CalibrationAnchor { region, text: line.text.clone(), rect: line.rect };
+
+    let mut anchors: Vec<CalibrationAnchor> = result
+        .lines
+        .iter()
+        .find(|line| language.contains(Label::MissionSummary, &line.text))
+        .map(|line| anchor(CropRegion::MissionSummary, line))
+        .into_iter()
+        .collect();
+
+    let mut cards: Vec<&OcrLine> = result
+        .lines
+        .iter()
+        .filter(|line| labels::find_card_label(language, &line.text).is_some())
+        .collect();
+    cards.sort_by_key(|line| line.rect.y);
+
+    // Lines whose tops are within one line height of each other share a row
+    let mut rows: Vec<Vec<&OcrLine>> = Vec::new();
+    for line in cards {
+        match rows.last_mut() {
+            Some(row) if (line.rect.y - row[0].rect.y).abs() < row[0].rect.height.max(1) => row.push(line),
+            _ => rows.push(vec![line]),
+        }
+    }
+
+    // Cards in a row with a single label found go by which side of the column split they are on
+    let (first_x, ..) = crop::base_rect(CropRegion::SummaryFirst);
+    let (second_x, ..) = crop::base_rect(CropRegion::SummarySecond);
+    let column_split = (first_x + second_x) as f32 / 2.0 * width as f32 / crop::BASE_WIDTH as f32;
+    let row_regions = [
+        [CropRegion::SummaryFirst, CropRegion::SummarySecond],
+        [CropRegion::SummaryThird, CropRegion::SummaryFourth],
+    ];
+
+    for (row, regions) in rows.iter_mut().zip(row_regions) {
+        row.sort_by_key(|line| line.rect.x);
+        match row.as_slice() {
+            [line] => {
+                let column = if (line.rect.x as f32) < column_split { 0 } else { 1 };
+                anchors.push(anchor(regions[column], line));
+            }
+            [left, right, ..] => {
+                anchors.push(anchor(regions[0], left));
+                anchors.push(anchor(regions[1], right));
+            }
+            [] => {}
+        }
+    }
+
+    anchors
+}
+
+/// Least squares fit of `found = offset + base * scale` on each axis.
+/// An axis where all labels share one coordinate keeps the proportional scale.
+fn fit(width: u32, height: u32, points: &[((f32, f32), (f32, f32))]) -> Result<Calibration, Box<dyn Error + Send + Sync>> {
+    let fit_axis = |pairs: Vec<(f32, f32)>, proportional: f32| {
+        let count = pairs.len() as f32;
+        let mean_base = pairs.iter().map(|p| p.0).sum::<f32>() / count;
+        let mean_found = pairs.iter().map(|p| p.1).sum::<f32>() / count;
+        let variance: f32 = pairs.iter().map(|p| (p.0 - mean_base).powi(2)).sum();
+        let covariance: f32 = pairs.iter().map(|p| (p.0 - mean_base) * (p.1 - mean_found)).sum();
+
+        let scale = if variance > 1.0 { covariance / variance } else { proportional };
+        (scale, mean_found - scale * mean_base)
+    };
+
+    let (scale_x, offset_x) = fit_axis(points.iter().map(|&((bx, _), (x, _))| (bx, x)).collect(), width as f32 / crop::BASE_WIDTH as f32);
+    let (scale_y, offset_y) = fit_axis(points.iter().map(|&((_, by), (_, y))| (by, y)).collect(), height as f32 / crop::BASE_HEIGHT as f32);
+
+    // A fit far from the proportional scale means labels were matched to the wrong cards
+    let plausible = |scale: f32, proportional: f32| scale.is_finite() && scale > proportional * 0.5 && scale < proportional * 2.0;
+    if !plausible(scale_x, width as f32 / crop::BASE_WIDTH as f32) || !plausible(scale_y, height as f32 / crop::BASE_HEIGHT as f32) {
+        return Err(AppError::new(
+            ErrorCode::InvalidInput,
+            format!("Calibration gave an implausible scale of {:.2}x{:.2}, check the screenshot", scale_x, scale_y),
+        ).into());
+    }
+
+    Ok(Calibration { width, height, scale_x, scale_y, offset_x, offset_y })
+}
+
+fn annotate(image: DynamicImage, regions: &[(CropRegion, Rect)], anchors: &[CalibrationAnchor]) -> DynamicImage {
+    let mut preview = image.to_rgba8();
+    for &(_, (x, y, width, height)) in regions {
+        debug::draw_box(&mut preview, x as i32, y as i32, width, height, REGION_COLOR);
+    }
+    for anchor in anchors {
+        let rect = anchor.rect;
+        if rect.width > 0 && rect.height > 0 {
+            debug::draw_box(&mut preview, rect.x, rect.y, rect.width as u32, rect.height as u32, WORD_COLOR);
+        }
+    }
+    DynamicImage::ImageRgba8(preview)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(text: &str, x: i32, y: i32) -> OcrLine {
+        OcrLine {
+            text: text.to_string(),
+            rect: TextRect { x, y, width: 200, height: 30 },
+            confidence: None,
+            words: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_calibrates_scaled_and_shifted_layout() {
+        // A 2560x1440 capture with the UI 20px lower than the proportional layout
+        let (scale, offset_y) = (2560.0 / 1920.0, 20.0);
+        let at = |region: CropRegion| {
+            let (x, y) = base_anchor(region).unwrap();
+            ((x * scale).round() as i32, (y * scale + offset_y).round() as i32)
+        };
+        let place = |text: &str, region: CropRegion| {
+            let (x, y) = at(region);
+            line(text, x, y)
+        };
+        let result = OcrResult {
+            lines: vec![
+                place("MISSION SUMMARY", CropRegion::MissionSummary),
+                place("HUNTERS KILLED", CropRegion::SummarySecond),
+                place("BOUNTY COLLECTED", CropRegion::SummaryFirst),
+                place("EXTRACTED", CropRegion::SummaryFourth),
+                line("1250", 400, 400),
+            ],
+        };
+
+        let anchors = find_labels(2560, &result, GameLanguage::English);
+        let regions: Vec<CropRegion> = anchors.iter().map(|anchor| anchor.region).collect();
+        assert_eq!(regions, vec![CropRegion::MissionSummary, CropRegion::SummaryFirst, CropRegion::SummarySecond, CropRegion::SummaryFourth]);
+
+        let points: Vec<_> = anchors
+            .iter()
+            .map(|anchor| (base_anchor(anchor.region).unwrap(), (anchor.rect.x as f32, anchor.rect.y as f32)))
+            .collect();
+        let calibration = fit(2560, 1440, &points).unwrap();
+
+        assert!((calibration.scale_x - scale).abs() < 0.01);
+        assert!((calibration.offset_y - offset_y).abs() < 1.0);
+        let (x, y, _, _) = calibration.rect(CropRegion::SummaryUsername);
+        assert_eq!(x, 213);
+        assert!((y as i32 - 868).abs() <= 1);
+    }
+}
@@ -1,103 +1,91 @@
-use crate::{crop, ocr};
-use tauri::Manager;
+use diesel::Connection;
+use crate::{ai_models, api, calibration, crop, diagnostics, encoding, labels, logging, ocr, overlay, pipeline, preprocess, privacy, reprocess, retention, stats, thumbnail, webhook};
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::fields;
+use tauri::Emitter;
+use tracing::{info, Instrument};
 
 #[tauri::command]
-pub async fn submit_screenshot(app_handle: tauri::AppHandle, screenshot_id: i32) -> Result<(), String> {
-    use crate::models::screenshots::dsl::{screenshots, id, image, recognized, summary_first, summary_second, summary_third, summary_fourth, summary_username};
-    use crate::models::screenshots::columns::ocr as ocr_column;
-    use diesel::prelude::*;
+pub async fn submit_screenshot(app_handle: tauri::AppHandle, screenshot_id: i32) -> AppResult<()> {
+    let extracted_fields = reprocess::recognize_screenshot(&app_handle, screenshot_id)
+        .instrument(tracing::info_span!("submit", screenshot_id))
+        .await
+        .map_err(|e| AppError::from(e).context(format!("Failed to process screenshot {}", screenshot_id)))?;
 
-    // Get database connection from app state
-    let app_state = app_handle.state::<crate::AppState>();
-    let db = app_state.inner().db.as_ref().ok_or("Database not initialized")?;
-    
-    // Get screenshot data first, then drop the connection
-    let screenshot_data: String = {
-        let mut conn = db.lock().map_err(|_| "Failed to lock database connection")?;
-        screenshots
-            .filter(id.eq(screenshot_id))
-            .select(image)
-            .first(&mut *conn)
-            .map_err(|e| format!("Failed to get screenshot: {}", e))?
-    };
+    // Webhooks only announce matches the user submitted, not re-processed ones
+    let _ = app_handle.emit(webhook::SUBMITTED_EVENT, screenshot_id);
 
-    println!("Processing screenshot ID: {}", screenshot_id);
-
-    // Define regions to process
-    let regions = vec![
-        (crop::CropRegion::SummaryFirst, "First Summary"),
-        (crop::CropRegion::SummarySecond, "Second Summary"),
-        (crop::CropRegion::SummaryThird, "Third Summary"),
-        (crop::CropRegion::SummaryFourth, "Fourth Summary"),
-        (crop::CropRegion::SummaryUsername, "Username"),
-    ];
-
-    // Process each region and collect results
-    let mut first_summary = String::new();
-    let mut second_summary = String::new();
-    let mut third_summary = String::new();
-    let mut fourth_summary = String::new();
-    let mut username = String::new();
-
-    // Process each region
-    for (region, region_name) in regions {
-        // Crop the region
-        let cropped_image = crop::crop_image(app_handle.clone(), screenshot_data.clone(), region)
-            .await
-            .map_err(|e| format!("Failed to crop {}: {}", region_name, e))?;
+    // Let the viewer highlight values that need a second look
+    let flagged: Vec<&fields::ExtractedField> = extracted_fields.iter().filter(|f| f.corrected || f.suspicious).collect();
+    if !flagged.is_empty() {
+        info!("Fields flagged for review: {:?}", flagged.iter().map(|f| f.region).collect::<Vec<_>>());
+        let _ = app_handle.emit("fields-flagged", (screenshot_id, &flagged));
+    }
 
-        // Perform OCR on the cropped region
-        let ocr_results = ocr::perform_ocr(app_handle.clone(), cropped_image)
-            .await
-            .map_err(|e| format!("Failed OCR for {}: {}", region_name, e))?;
-
-        // Store results in the appropriate variable
-        let result_text = ocr_results.join(" ");
-        match region {
-            crop::CropRegion::SummaryFirst => first_summary = result_text.clone(),
-            crop::CropRegion::SummarySecond => second_summary = result_text.clone(),
-            crop::CropRegion::SummaryThird => third_summary = result_text.clone(),
-            crop::CropRegion::SummaryFourth => fourth_summary = result_text.clone(),
-            crop::CropRegion::SummaryUsername => username = result_text.clone(),
-            _ => (), // Ignore any other regions
-        }
+    Ok(())
+}
 
-        // Print OCR results
-        println!("OCR Results for {}: {}", region_name, result_text);
-    }
+#[tauri::command]
+pub async fn get_match_fields(app_handle: tauri::AppHandle, screenshot_id: i32) -> AppResult<Vec<crate::models::MatchFieldRecord>> {
+    crate::db::run(&app_handle, move |conn| crate::db::get_match_fields(conn, screenshot_id))
+        .await
+        .map_err(|e| e.context("Failed to load match fields"))
+}
 
-    // Update the screenshot record with all OCR results
-    {
-        let mut conn = db.lock().map_err(|_| "Failed to lock database connection")?;
-        diesel::update(screenshots.filter(id.eq(screenshot_id)))
-            .set((
-                recognized.eq(true),
-                ocr_column.eq(true),
-                summary_first.eq(first_summary),
-                summary_second.eq(second_summary),
-                summary_third.eq(third_summary),
-                summary_fourth.eq(fourth_summary),
-                summary_username.eq(username),
-            ))
-            .execute(&mut *conn)
-            .map_err(|e| format!("Failed to update screenshot status: {}", e))?;
-    }
+/// Applies manual corrections, keyed by region name or `mission_type`, and returns the updated fields
+#[tauri::command]
+pub async fn update_match_fields(app_handle: tauri::AppHandle, screenshot_id: i32, fields: std::collections::HashMap<String, String>) -> AppResult<Vec<crate::models::MatchFieldRecord>> {
+    crate::db::run(&app_handle, move |conn| {
+        // All or nothing, an invalid field must not leave the ones before it applied
+        conn.transaction::<_, AppError, _>(|conn| {
+            for (field, new_value) in &fields {
+                crate::db::correct_match_field(conn, screenshot_id, field, new_value)
+                    .map_err(|e| AppError::from(e).context(format!("Failed to update {}", field)))?;
+            }
+            Ok(())
+        })?;
 
-    Ok(())
+        crate::db::get_match_fields(conn, screenshot_id)
+            .map_err(|e| AppError::from(e).context("Failed to load match fields"))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_corrections(app_handle: tauri::AppHandle, screenshot_id: i32) -> AppResult<Vec<crate::models::Correction>> {
+    crate::db::run(&app_handle, move |conn| crate::db::get_corrections(conn, screenshot_id))
+        .await
+        .map_err(|e| e.context("Failed to load corrections"))
+}
+
+/// Match totals using corrected values, `since` is an ISO date or datetime
+#[tauri::command]
+pub async fn get_stats(app_handle: tauri::AppHandle, since: Option<String>) -> AppResult<stats::MatchStats> {
+    let since = stats::parse_since(since.as_deref())?;
+
+    crate::db::run(&app_handle, move |conn| stats::collect(conn, since))
+        .await
+        .map_err(|e| e.context("Failed to compute stats"))
+}
+
+#[tauri::command]
+pub async fn get_ocr_results(app_handle: tauri::AppHandle, screenshot_id: i32) -> AppResult<Vec<(String, ocr::OcrResult)>> {
+    crate::db::run(&app_handle, move |conn| crate::db::get_ocr_results(conn, screenshot_id))
+        .await
+        .map_err(|e| e.context("Failed to load OCR results"))
 }
 
 #[tauri::command]
-pub async fn reload_shortcut(app_handle: tauri::AppHandle) -> Result<(), String> {
+pub async fn reload_shortcut(app_handle: tauri::AppHandle) -> AppResult<()> {
     use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, Modifiers, Code};
     use crate::shortcuts::{get_shortcut, format_key_for_code, register_shortcut};
 
     // Unregister all existing shortcuts
     app_handle.global_shortcut().unregister_all()
-        .map_err(|e| format!("Failed to unregister shortcuts: {}", e))?;
+        .map_err(|e| AppError::new(ErrorCode::Internal, format!("Failed to unregister shortcuts: {}", e)))?;
 
     // Get the new shortcut from settings
-    let shortcut_str = get_shortcut(&app_handle)
-        .map_err(|e| format!("Failed to get shortcut: {}", e))?;
+    let shortcut_str = get_shortcut(&app_handle).await;
 
     // Parse the shortcut string
     let parts: Vec<&str> = shortcut_str.split('+').collect();
@@ -121,14 +109,188 @@ pub async fn reload_shortcut(app_handle: tauri::AppHandle) -> Result<(), String>
         Some(input_key) => {
             let formatted_key = format_key_for_code(&input_key);
             formatted_key.parse::<Code>()
-                .map_err(|_| format!("Invalid key in shortcut: {}", input_key))?
+                .map_err(|_| AppError::new(ErrorCode::InvalidInput, format!("Invalid key in shortcut: {}", input_key)))?
         }
-        None => return Err("No key specified in shortcut".into()),
+        None => return Err(AppError::new(ErrorCode::InvalidInput, "No key specified in shortcut")),
     };
 
     let shortcut = Shortcut::new(Some(modifiers), code);
     register_shortcut(&app_handle, shortcut)
-        .map_err(|e| format!("Failed to register shortcut: {}", e))?;
+        .map_err(|e| AppError::from(e).context("Failed to register shortcut"))?;
 
     Ok(())
-}
\ No newline at end of file
+}
+
+#[tauri::command]
+pub async fn get_disk_usage(app_handle: tauri::AppHandle) -> AppResult<Vec<retention::DiskUsage>> {
+    tokio::task::spawn_blocking(move || retention::disk_usage(&app_handle))
+        .await?
+        .map_err(|e| AppError::from(e).context("Failed to read disk usage"))
+}
+
+#[tauri::command]
+pub async fn apply_retention(app_handle: tauri::AppHandle) -> AppResult<usize> {
+    tokio::task::spawn_blocking(move || retention::apply_retention(&app_handle))
+        .await?
+        .map_err(|e| AppError::from(e).context("Failed to apply retention policy"))
+}
+
+
+#[tauri::command]
+pub async fn preview_preprocessing(
+    app_handle: tauri::AppHandle,
+    base64_image: String,
+    region: crop::CropRegion,
+    steps: Option<Vec<preprocess::PreprocessStep>>,
+) -> AppResult<Vec<preprocess::StepPreview>> {
+    tokio::task::spawn_blocking(move || crop::preview_preprocess(&app_handle, &base64_image, region, steps))
+        .await?
+        .map_err(|e| AppError::from(e).context("Failed to preview preprocessing"))
+}
+
+#[tauri::command]
+pub async fn get_model_status(app_handle: tauri::AppHandle) -> AppResult<Vec<ai_models::ModelStatus>> {
+    tokio::task::spawn_blocking(move || ai_models::verify_models(&app_handle))
+        .await?
+        .map_err(|e| AppError::from(e).context("Failed to verify OCR models"))
+}
+
+#[tauri::command]
+pub async fn compare_ocr_backends(app_handle: tauri::AppHandle, screenshot_id: i32) -> AppResult<Vec<pipeline::BackendComparison>> {
+    use crate::models::screenshots::dsl::{screenshots, id, image, image_format};
+    use diesel::prelude::*;
+
+    let (screenshot_data, format): (String, Option<String>) = crate::db::run(&app_handle, move |conn| {
+        screenshots.filter(id.eq(screenshot_id)).select((image, image_format)).first(conn)
+    })
+    .await
+    .map_err(|e| e.context("Failed to get screenshot"))?;
+    let format = format.as_deref().and_then(encoding::CaptureFormat::parse);
+
+    pipeline::compare_backends(&app_handle, &screenshot_data, format, &reprocess::SUMMARY_REGIONS)
+        .await
+        .map_err(|e| AppError::from(e).context("Failed to compare OCR backends"))
+}
+
+/// Re-runs crop and OCR on the given screenshots, or all of them, in the background.
+/// Progress is reported with `reprocess-progress`, returns the number of queued screenshots.
+#[tauri::command]
+pub async fn reprocess_matches(app_handle: tauri::AppHandle, ids: Option<Vec<i32>>) -> AppResult<usize> {
+    reprocess::start(&app_handle, ids)
+        .await
+        .map_err(|e| AppError::from(e).context("Failed to start re-processing"))
+}
+
+#[tauri::command]
+pub async fn cancel_reprocess() -> AppResult<bool> {
+    let running = reprocess::is_running();
+    if running {
+        reprocess::cancel();
+    }
+    Ok(running)
+}
+
+/// Recent log entries at or above `level` (default `info`), newest first
+#[tauri::command]
+pub async fn get_logs(app_handle: tauri::AppHandle, level: Option<String>, limit: Option<usize>) -> AppResult<Vec<logging::LogEntry>> {
+    let min_level = match level.as_deref() {
+        Some(value) => logging::parse_level(value)
+            .ok_or_else(|| AppError::new(ErrorCode::InvalidInput, format!("Unknown log level: {}", value)))?,
+        None => tracing::Level::INFO,
+    };
+    let dir = logging::log_dir(&app_handle)?;
+
+    tokio::task::spawn_blocking(move || logging::read_logs(&dir, min_level, limit.unwrap_or(200)))
+        .await?
+        .map_err(|e| AppError::from(e).context("Failed to read logs"))
+}
+
+/// Writes a zip with logs, settings, model status and recent captures for bug reports, returns its path
+#[tauri::command]
+pub async fn create_diagnostics_bundle(app_handle: tauri::AppHandle, redact: Option<bool>) -> AppResult<String> {
+    let redact = redact.unwrap_or(true);
+    let privacy = privacy::Privacy::configured(&app_handle).await;
+    tokio::task::spawn_blocking(move || diagnostics::create_bundle(&app_handle, redact, &privacy))
+        .await?
+        .map(|path| path.to_string_lossy().into_owned())
+        .map_err(|e| AppError::from(e).context("Failed to create diagnostics bundle"))
+}
+
+/// Fits the crop regions to a known mission summary screenshot and saves them for its resolution.
+/// Returns the calibrated regions with an annotated preview to confirm them.
+#[tauri::command]
+pub async fn calibrate_regions(app_handle: tauri::AppHandle, base64_image: String) -> AppResult<calibration::CalibrationResult> {
+    let language = labels::configured_language(&app_handle).await;
+    tokio::task::spawn_blocking(move || calibration::calibrate(&app_handle, &base64_image, language))
+        .await?
+        .map_err(|e| AppError::from(e).context("Failed to calibrate regions"))
+}
+
+/// Scores OCR accuracy and storage size of every capture format on a corpus of summary screenshots.
+/// The corpus defaults to `AppData/corpus` and needs an `expected.json` with the values per image.
+#[tauri::command]
+pub async fn compare_capture_formats(app_handle: tauri::AppHandle, corpus_dir: Option<String>) -> AppResult<encoding::FormatReport> {
+    let corpus = match corpus_dir {
+        Some(dir) => std::path::PathBuf::from(dir),
+        None => encoding::corpus_dir(&app_handle)?,
+    };
+
+    encoding::compare_formats(&app_handle, &corpus)
+        .await
+        .map_err(|e| AppError::from(e).context("Failed to compare capture formats"))
+}
+
+/// Base64 JPEG thumbnail of a screenshot, or the preview crop of `region` when given
+#[tauri::command]
+pub async fn get_thumbnail(app_handle: tauri::AppHandle, screenshot_id: i32, region: Option<crop::CropRegion>) -> AppResult<String> {
+    thumbnail::get(&app_handle, screenshot_id, region)
+        .await
+        .map_err(|e| AppError::from(e).context(format!("Failed to load thumbnail for screenshot {}", screenshot_id)))
+}
+
+/// Restarts the local API so changed `api_*` settings take effect
+#[tauri::command]
+pub async fn reload_api(app_handle: tauri::AppHandle) -> AppResult<()> {
+    api::reload(&app_handle).await;
+    Ok(())
+}
+
+/// The local API token, generated on first use, for the settings view to reveal or copy
+#[tauri::command]
+pub async fn get_api_token(app_handle: tauri::AppHandle) -> AppResult<String> {
+    api::ensure_token(&app_handle).await.map_err(|e| e.context("Failed to read the API token"))
+}
+
+/// Restarts the stream overlay so changed `overlay_*` settings take effect
+#[tauri::command]
+pub async fn reload_overlay(app_handle: tauri::AppHandle) -> AppResult<()> {
+    overlay::reload(&app_handle).await;
+    Ok(())
+}
+
+/// Posts a sample match to `url`, or to every configured webhook when no URL is given
+#[tauri::command]
+pub async fn send_test_webhook(app_handle: tauri::AppHandle, url: Option<String>) -> AppResult<()> {
+    let urls = match url.filter(|url| !url.trim().is_empty()) {
+        Some(url) => vec![url.trim().to_string()],
+        None => webhook::configured_urls(&app_handle).await,
+    };
+    if urls.is_empty() {
+        return Err(AppError::new(ErrorCode::InvalidInput, "No webhook URL configured"));
+    }
+
+    for url in urls {
+        webhook::send_test(&url)
+            .await
+            .map_err(|e| AppError::from(e).context(format!("Failed to send test webhook to {}", url)))?;
+    }
+    Ok(())
+}
+
+/// Applies the current privacy settings to every stored screenshot, returns how many were rewritten
+#[tauri::command]
+pub async fn redact_stored_screenshots(app_handle: tauri::AppHandle) -> AppResult<usize> {
+    privacy::redact_stored(&app_handle)
+        .await
+        .map_err(|e| AppError::from(e).context("Failed to redact stored screenshots"))
+}
@@ -1,9 +1,18 @@
 use image::{GenericImageView, ImageError, DynamicImage};
 use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
-use std::io::Cursor;
-use tauri::{path::BaseDirectory, AppHandle, Emitter, Manager, Runtime};
+use tauri::{AppHandle, Emitter, Runtime};
+use tracing::warn;
 
-#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+use crate::db;
+use crate::encoding::{self, CaptureFormat};
+use crate::error::{AppError, AppResult};
+use crate::preprocess::{self, PreprocessStep, StepPreview};
+
+/// Resolution the crop configs are measured at, also the game's aspect ratio
+pub const BASE_WIDTH: u32 = 1920;
+pub const BASE_HEIGHT: u32 = 1080;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub enum CropRegion {
     MissionSummary,
 
@@ -14,6 +23,25 @@ pub enum CropRegion {
     SummaryUsername,
 }
 
+impl CropRegion {
+    pub const ALL: [CropRegion; 6] = [
+        CropRegion::MissionSummary,
+        CropRegion::SummaryFirst,
+        CropRegion::SummarySecond,
+        CropRegion::SummaryThird,
+        CropRegion::SummaryFourth,
+        CropRegion::SummaryUsername,
+    ];
+
+    /// Whether the region holds a summary card whose value must be a number
+    pub fn is_numeric(&self) -> bool {
+        matches!(
+            self,
+            CropRegion::SummaryFirst | CropRegion::SummarySecond | CropRegion::SummaryThird | CropRegion::SummaryFourth
+        )
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 struct CropConfig {
     x: u32,
@@ -65,13 +93,51 @@ fn get_crop_config(region: CropRegion) -> CropConfig {
     }
 }
 
+/// Parses a region from the name it is stored under, e.g. `SummaryFirst`
+pub fn region_from_name(name: &str) -> Option<CropRegion> {
+    CropRegion::ALL.into_iter().find(|region| format!("{:?}", region) == name)
+}
+
+/// Default preprocessing applied to each region before OCR, overridable with the
+/// `preprocess_<Region>` setting holding a JSON list of steps
+fn default_preprocess(region: CropRegion) -> Vec<PreprocessStep> {
+    match region {
+        // The summary cards show light numbers on a dark background
+        CropRegion::SummaryFirst
+        | CropRegion::SummarySecond
+        | CropRegion::SummaryThird
+        | CropRegion::SummaryFourth => vec![
+            PreprocessStep::Grayscale,
+            PreprocessStep::Upscale { factor: 2.0 },
+            PreprocessStep::Invert,
+            PreprocessStep::Contrast { amount: 30.0 },
+        ],
+
+        CropRegion::MissionSummary | CropRegion::SummaryUsername => vec![
+            PreprocessStep::Contrast { amount: 30.0 },
+        ],
+    }
+}
+
+/// Returns the preprocessing chain configured for a region
+pub fn region_preprocess<R: Runtime>(app: &AppHandle<R>, region: CropRegion) -> Vec<PreprocessStep> {
+    let setting_key = format!("preprocess_{:?}", region);
+    match db::read_setting(app, &setting_key) {
+        Some(json) if !json.trim().is_empty() => preprocess::parse_steps(&json).unwrap_or_else(|e| {
+            warn!("Invalid {} setting, using defaults: {}", setting_key, e);
+            default_preprocess(region)
+        }),
+        _ => default_preprocess(region),
+    }
+}
+
 fn calculate_proportional_dimensions(
     original_width: u32,
     original_height: u32,
     config: &CropConfig,
 ) -> (u32, u32, u32, u32) {
-    let width_ratio = original_width as f32 / 1920.0;
-    let height_ratio = original_height as f32 / 1080.0;
+    let width_ratio = original_width as f32 / BASE_WIDTH as f32;
+    let height_ratio = original_height as f32 / BASE_HEIGHT as f32;
 
     let new_x = (config.x as f32 * width_ratio).round() as u32;
     let new_y = (config.y as f32 * height_ratio).round() as u32;
@@ -81,69 +147,90 @@ fn calculate_proportional_dimensions(
     (new_x, new_y, new_width, new_height)
 }
 
+/// The `(x, y, width, height)` rectangle of a region in the 1920x1080 layout
+pub fn base_rect(region: CropRegion) -> (u32, u32, u32, u32) {
+    let config = get_crop_config(region);
+    (config.x, config.y, config.width, config.height)
+}
+
+/// Returns the `(x, y, width, height)` rectangle of a region for an image of the given size
+pub fn region_rect(width: u32, height: u32, region: CropRegion) -> (u32, u32, u32, u32) {
+    calculate_proportional_dimensions(width, height, &get_crop_config(region))
+}
+
 #[tauri::command(async)]
-pub async fn crop_image<R: Runtime>(app: AppHandle<R>, base64_image: String, region: CropRegion) -> Result<String, String> {
+pub async fn crop_image<R: Runtime>(app: AppHandle<R>, base64_image: String, region: CropRegion) -> AppResult<String> {
     let app_handle = app.clone();
+    let format = encoding::configured(&app).await;
     
     // Spawn a new thread for image cropping
     tokio::task::spawn_blocking(move || {
-        let result = process_crop(&app, &base64_image, region);
+        let result = process_crop(&app, &base64_image, region, format);
         
         match result {
             Ok(cropped_image) => {
                 // Emit an event when cropping is complete
                 if let Err(e) = app_handle.emit("crop-complete", &cropped_image) {
-                    println!("Failed to emit crop complete event: {}", e);
+                    warn!("Failed to emit crop complete event: {}", e);
                 }
                 Ok(cropped_image)
             }
             Err(e) => {
                 // Emit an error event
-                if let Err(emit_err) = app_handle.emit("crop-error", e.to_string()) {
-                    println!("Failed to emit crop error event: {}", emit_err);
+                let error = AppError::from(e);
+                if let Err(emit_err) = app_handle.emit("crop-error", &error) {
+                    warn!("Failed to emit crop error event: {}", emit_err);
                 }
-                Err(e.to_string())
+                Err(error)
             }
         }
-    }).await.unwrap_or_else(|e| Err(e.to_string()))
+    }).await?
 }
 
-pub fn process_crop<R: Runtime>(app: &AppHandle<R>, base64_image: &str, region: CropRegion) -> Result<String, ImageError> {
-    // Decode base64 image
+pub fn process_crop<R: Runtime>(app: &AppHandle<R>, base64_image: &str, region: CropRegion, format: CaptureFormat) -> Result<String, ImageError> {
+    let img = decode_image(base64_image)?;
+    let cropped = crop_region(&img, region);
+    
+    // Prepare the crop for OCR using the region's preprocessing chain
+    let enhanced = preprocess::apply_all(cropped, &region_preprocess(app, region));
+
+    // Crops use the capture format so a lossless capture isn't degraded on the way to OCR
+    let data = format.encode(&enhanced)?;
+    let encoded = BASE64.encode(data);
+    
+    Ok(encoded)
+}
+
+/// Crops a region and returns the image after every preprocessing step
+pub fn preview_preprocess<R: Runtime>(app: &AppHandle<R>, base64_image: &str, region: CropRegion, steps: Option<Vec<PreprocessStep>>) -> Result<Vec<StepPreview>, ImageError> {
+    let img = decode_image(base64_image)?;
+    let cropped = crop_region(&img, region);
+    let steps = steps.unwrap_or_else(|| region_preprocess(app, region));
+
+    preprocess::preview_steps(cropped, &steps)
+}
+
+/// Decodes a base64 encoded image, guessing its format
+pub fn decode_image(base64_image: &str) -> Result<DynamicImage, ImageError> {
+    decode_image_as(base64_image, None)
+}
+
+/// Decodes a base64 encoded image stored in a known format
+pub fn decode_image_as(base64_image: &str, format: Option<CaptureFormat>) -> Result<DynamicImage, ImageError> {
     let image_data = BASE64.decode(base64_image).map_err(|e| {
         ImageError::IoError(std::io::Error::new(std::io::ErrorKind::InvalidData, e))
     })?;
 
-    // Load the image
-    let img = image::load_from_memory(&image_data)?;
-    
-    // Get dimensions
+    encoding::decode(&image_data, format)
+}
+
+/// Crops a region out of a full capture
+pub fn crop_region(img: &DynamicImage, region: CropRegion) -> DynamicImage {
     let (width, height) = img.dimensions();
-    
-    // Get crop configuration
-    let config = get_crop_config(region);
-    
-    // Calculate proportional dimensions
-    let (x, y, crop_width, crop_height) = calculate_proportional_dimensions(width, height, &config);
-    
-    // Crop the image
-    let cropped = img.crop_imm(x, y, crop_width, crop_height);
-    
-    // Enhance contrast for OCR using image::DynamicImage::adjust_contrast
-    let enhanced = DynamicImage::ImageRgba8(cropped.into()).adjust_contrast(30.0);
-
-    // Save the enhanced image as JPEG for debugging
-    // Create debug directory if it doesn't exist
-    let debug_path = app.path().resolve("debug_images", BaseDirectory::AppData)
-        .map_err(|e| ImageError::IoError(std::io::Error::new(std::io::ErrorKind::Other, e.to_string())))?;
-    std::fs::create_dir_all(&debug_path).map_err(|e| ImageError::IoError(e))?;
-    let filename = format!("{}/{:?}.jpg", debug_path.to_str().unwrap(), region);
-    enhanced.to_rgb8().save(&filename)?;
-
-    let mut buffer = Cursor::new(Vec::new());
-    let mut encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut buffer, 85);
-    encoder.encode_image(&enhanced.to_rgb8())?;
-    let encoded = BASE64.encode(buffer.into_inner());
-    
-    Ok(encoded)
-}
\ No newline at end of file
+    crop_rect(img, region_rect(width, height, region))
+}
+
+/// Crops an `(x, y, width, height)` rectangle out of a full capture
+pub fn crop_rect(img: &DynamicImage, (x, y, crop_width, crop_height): (u32, u32, u32, u32)) -> DynamicImage {
+    DynamicImage::ImageRgba8(img.crop_imm(x, y, crop_width, crop_height).into())
+}
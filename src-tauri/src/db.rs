@@ -1,22 +1,66 @@
+use diesel::connection::SimpleConnection;
 use diesel::prelude::*;
-use tauri::{AppHandle, Manager, path::BaseDirectory};
+use diesel::r2d2::{ConnectionManager, CustomizeConnection, Pool};
+use tauri::{AppHandle, Manager, Runtime, path::BaseDirectory};
 use chrono::Local;
+use tracing::warn;
 
-use crate::models::{Setting, screenshots, Screenshot};
+use crate::crop::{self, CropRegion};
+use crate::encoding::CaptureFormat;
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::fields::{self, ExtractedField};
+use crate::labels::{GameLanguage, Label};
+use crate::models::{Setting, screenshots, Screenshot, ocr_results, RegionOcr, match_fields, MatchField, MatchFieldRecord, corrections, Correction, region_previews, RegionPreview};
+use crate::thumbnail::{self, Previews};
+use crate::ocr::OcrResult;
 
 // Type alias for the database connection
 pub type DbConnection = SqliteConnection;
+pub type DbPool = Pool<ConnectionManager<DbConnection>>;
+pub type PooledConnection = diesel::r2d2::PooledConnection<ConnectionManager<DbConnection>>;
 
-pub fn init(app: &AppHandle) -> DbConnection {
-    let database_url = get_db_path(app).unwrap();
-    SqliteConnection::establish(&database_url)
-        .expect("Failed to create database connection")
+/// Connections shared by commands, OCR jobs and background tasks
+const POOL_SIZE: u32 = 4;
+/// How long a statement waits for another writer before failing with "database is locked"
+const BUSY_TIMEOUT_MS: u32 = 5000;
+
+/// Applied to every pooled connection. WAL lets readers, including the connection
+/// `tauri_plugin_sql` opens for the frontend, proceed while a writer is active.
+#[derive(Debug)]
+struct SqlitePragmas;
+
+impl CustomizeConnection<DbConnection, diesel::r2d2::Error> for SqlitePragmas {
+    fn on_acquire(&self, conn: &mut DbConnection) -> Result<(), diesel::r2d2::Error> {
+        conn.batch_execute(&format!(
+            "PRAGMA journal_mode = WAL; PRAGMA synchronous = NORMAL; PRAGMA busy_timeout = {};",
+            BUSY_TIMEOUT_MS
+        ))
+        .map_err(diesel::r2d2::Error::QueryError)
+    }
 }
 
-pub fn save_screenshot(conn: &mut DbConnection, image_data: String, mission_type: String) -> Result<i32, diesel::result::Error> {
+pub fn init(app: &AppHandle) -> Result<DbPool, Box<dyn std::error::Error + Send + Sync>> {
+    let manager = ConnectionManager::<DbConnection>::new(get_db_path(app)?);
+    let pool = Pool::builder()
+        .max_size(POOL_SIZE)
+        .connection_customizer(Box::new(SqlitePragmas))
+        .build(manager)?;
+    Ok(pool)
+}
+
+pub fn save_screenshot(conn: &mut DbConnection, image_data: String, format: CaptureFormat, mission_type: String, language: Option<GameLanguage>) -> Result<i32, diesel::result::Error> {
+    // Previews are only for browsing, a capture that fails to render them is still saved
+    let previews = match thumbnail::render(&image_data, Some(format)) {
+        Ok(previews) => Some(previews),
+        Err(e) => {
+            warn!("Failed to render screenshot previews: {}", e);
+            None
+        }
+    };
+
     let new_screenshot = Screenshot {
         id: None,
-        name: "screenshot.jpg".to_string(),
+        name: format!("screenshot.{}", format.extension()),
         mission_type,
         image: image_data,
         recognized: false,
@@ -27,6 +71,10 @@ pub fn save_screenshot(conn: &mut DbConnection, image_data: String, mission_type
         summary_third: None,
         summary_fourth: None,
         summary_username: None,
+        language: language.map(|l| l.code().to_string()),
+        image_format: Some(format.name()),
+        thumbnail: previews.as_ref().map(|previews| previews.thumbnail.clone()),
+        manual_mission_type: None,
     };
     
     diesel::insert_into(screenshots::table)
@@ -36,15 +84,252 @@ pub fn save_screenshot(conn: &mut DbConnection, image_data: String, mission_type
     // Get the last inserted id
     let last_id = diesel::select(diesel::dsl::sql::<diesel::sql_types::Integer>("last_insert_rowid()"))
         .get_result(conn)?;
+
+    if let Some(previews) = &previews {
+        save_previews(conn, last_id, previews)?;
+    }
     
     Ok(last_id)
 }
 
+/// Stores the thumbnail and region previews of a screenshot, replacing existing ones
+pub fn save_previews(conn: &mut DbConnection, screenshot_id: i32, previews: &Previews) -> Result<(), diesel::result::Error> {
+    conn.transaction(|conn| {
+        diesel::update(screenshots::table.filter(screenshots::id.eq(screenshot_id)))
+            .set(screenshots::thumbnail.eq(&previews.thumbnail))
+            .execute(conn)?;
+
+        diesel::delete(region_previews::table.filter(region_previews::screenshot_id.eq(screenshot_id))).execute(conn)?;
+        let now = Local::now().naive_local();
+        for (region, image) in &previews.regions {
+            diesel::insert_into(region_previews::table)
+                .values(&RegionPreview {
+                    id: None,
+                    screenshot_id,
+                    region: format!("{:?}", region),
+                    image: image.clone(),
+                    created_at: now,
+                })
+                .execute(conn)?;
+        }
+        Ok(())
+    })
+}
+
+/// Stores the structured OCR result of a region, replacing any previous result for it
+pub fn save_ocr_result(conn: &mut DbConnection, screenshot_id: i32, region: CropRegion, result: &OcrResult) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let region_name = format!("{:?}", region);
+    let record = RegionOcr {
+        id: None,
+        screenshot_id,
+        region: region_name.clone(),
+        result: serde_json::to_string(result)?,
+        created_at: Local::now().naive_local(),
+    };
+
+    conn.transaction(|conn| {
+        diesel::delete(
+            ocr_results::table
+                .filter(ocr_results::screenshot_id.eq(screenshot_id))
+                .filter(ocr_results::region.eq(&region_name)),
+        )
+        .execute(conn)?;
+
+        diesel::insert_into(ocr_results::table)
+            .values(&record)
+            .execute(conn)
+    })?;
+
+    Ok(())
+}
+
+/// Loads the stored OCR results of a screenshot, keyed by region name
+pub fn get_ocr_results(conn: &mut DbConnection, screenshot_id: i32) -> Result<Vec<(String, OcrResult)>, Box<dyn std::error::Error + Send + Sync>> {
+    let records: Vec<(String, String)> = ocr_results::table
+        .filter(ocr_results::screenshot_id.eq(screenshot_id))
+        .order(ocr_results::id.asc())
+        .select((ocr_results::region, ocr_results::result))
+        .load(conn)?;
+
+    let mut results = Vec::new();
+    for (region_name, json) in records {
+        let result: OcrResult = serde_json::from_str(&json)?;
+        results.push((region_name, result));
+    }
+
+    Ok(results)
+}
+
+/// Stores the fields extracted from a screenshot. OCR columns are replaced, manual corrections are kept.
+pub fn save_match_fields(conn: &mut DbConnection, screenshot_id: i32, fields: &[ExtractedField]) -> Result<(), diesel::result::Error> {
+    conn.transaction(|conn| {
+        for field in fields {
+            let region_name = format!("{:?}", field.region);
+            let label = field.label.map(|label| label.as_str().to_string());
+            let existing = match_fields::table
+                .filter(match_fields::screenshot_id.eq(screenshot_id))
+                .filter(match_fields::region.eq(&region_name));
+
+            let updated = diesel::update(existing)
+                .set((
+                    match_fields::label.eq(&label),
+                    match_fields::raw_text.eq(&field.raw_text),
+                    match_fields::value.eq(&field.value),
+                    match_fields::number.eq(field.number),
+                    match_fields::corrected.eq(field.corrected),
+                    match_fields::suspicious.eq(field.suspicious),
+                ))
+                .execute(conn)?;
+
+            if updated == 0 {
+                diesel::insert_into(match_fields::table)
+                    .values(&MatchField {
+                        id: None,
+                        screenshot_id,
+                        region: region_name,
+                        label,
+                        raw_text: field.raw_text.clone(),
+                        value: field.value.clone(),
+                        number: field.number,
+                        corrected: field.corrected,
+                        suspicious: field.suspicious,
+                        created_at: Local::now().naive_local(),
+                    })
+                    .execute(conn)?;
+            }
+        }
+
+        Ok(())
+    })
+}
+
+/// Applies a manual correction to a field and records it in the `corrections` table.
+/// `field` is a region name such as `SummaryFirst`, `<region>.label` to set the card
+/// heading of a numeric region, or `mission_type`.
+pub fn correct_match_field(conn: &mut DbConnection, screenshot_id: i32, field: &str, new_value: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let new_value = new_value.trim();
+
+    conn.transaction(|conn| {
+        let old_value = if field == "mission_type" {
+            // The detected type stays in `mission_type`, like the OCR columns of region fields
+            let (detected, manual): (String, Option<String>) = screenshots::table
+                .filter(screenshots::id.eq(screenshot_id))
+                .select((screenshots::mission_type, screenshots::manual_mission_type))
+                .first(conn)?;
+
+            diesel::update(screenshots::table.filter(screenshots::id.eq(screenshot_id)))
+                .set(screenshots::manual_mission_type.eq(Some(new_value)))
+                .execute(conn)?;
+
+            Some(manual.unwrap_or(detected))
+        } else if let Some(region_name) = field.strip_suffix(".label") {
+            let region = crop::region_from_name(region_name)
+                .filter(|region| region.is_numeric())
+                .ok_or_else(|| AppError::new(ErrorCode::InvalidInput, format!("Unknown field: {}", field)))?;
+            let label = Label::parse(new_value)
+                .filter(|label| Label::CARDS.contains(label))
+                .ok_or_else(|| AppError::new(ErrorCode::InvalidInput, format!("Unknown card label: {}", new_value)))?;
+            let record = find_or_create_match_field(conn, screenshot_id, region)?;
+
+            diesel::update(match_fields::table.filter(match_fields::id.eq(record.id)))
+                .set(match_fields::manual_label.eq(Some(label.as_str())))
+                .execute(conn)?;
+
+            record.effective_label().map(str::to_string)
+        } else {
+            let region = crop::region_from_name(field).ok_or_else(|| AppError::new(ErrorCode::InvalidInput, format!("Unknown field: {}", field)))?;
+            let record = find_or_create_match_field(conn, screenshot_id, region)?;
+
+            // Numeric cards only accept whole numbers
+            let manual_number = if region.is_numeric() {
+                let digits: String = new_value.chars().filter(|c| !matches!(c, ',' | '.' | ' ' | '\'')).collect();
+                Some(digits.parse::<i64>().map_err(|_| AppError::new(ErrorCode::InvalidInput, format!("{} must be a whole number", field)))?)
+            } else {
+                None
+            };
+
+            diesel::update(match_fields::table.filter(match_fields::id.eq(record.id)))
+                .set((
+                    match_fields::manual_value.eq(Some(new_value)),
+                    match_fields::manual_number.eq(manual_number),
+                ))
+                .execute(conn)?;
+
+            Some(record.effective_value().to_string())
+        };
+
+        diesel::insert_into(corrections::table)
+            .values(&Correction {
+                id: None,
+                screenshot_id,
+                field: field.to_string(),
+                old_value,
+                new_value: new_value.to_string(),
+                created_at: Local::now().naive_local(),
+            })
+            .execute(conn)?;
+
+        Ok(())
+    })
+}
+
+/// The stored field of a region. Screenshots recognized before fields were stored only
+/// have the summary text, the field is extracted from it and saved first.
+fn find_or_create_match_field(conn: &mut DbConnection, screenshot_id: i32, region: CropRegion) -> Result<MatchFieldRecord, Box<dyn std::error::Error + Send + Sync>> {
+    let region_name = format!("{:?}", region);
+    let find = |conn: &mut DbConnection| {
+        match_fields::table
+            .filter(match_fields::screenshot_id.eq(screenshot_id))
+            .filter(match_fields::region.eq(&region_name))
+            .select(MatchFieldRecord::as_select())
+            .first(conn)
+            .optional()
+    };
+
+    if let Some(record) = find(conn)? {
+        return Ok(record);
+    }
+    let extracted = extract_from_summary(conn, screenshot_id, region)?;
+    save_match_fields(conn, screenshot_id, &[extracted])?;
+    find(conn)?.ok_or_else(|| AppError::new(ErrorCode::NotFound, format!("No recognized value for {} on screenshot {}", region_name, screenshot_id)).into())
+}
+
+/// Extracts a field from the flattened OCR text in the screenshot's `summary_*` column
+fn extract_from_summary(conn: &mut DbConnection, screenshot_id: i32, region: CropRegion) -> Result<ExtractedField, Box<dyn std::error::Error + Send + Sync>> {
+    let screenshot = || screenshots::table.filter(screenshots::id.eq(screenshot_id));
+    let summary: Option<String> = match region {
+        CropRegion::SummaryFirst => screenshot().select(screenshots::summary_first).first(conn)?,
+        CropRegion::SummarySecond => screenshot().select(screenshots::summary_second).first(conn)?,
+        CropRegion::SummaryThird => screenshot().select(screenshots::summary_third).first(conn)?,
+        CropRegion::SummaryFourth => screenshot().select(screenshots::summary_fourth).first(conn)?,
+        CropRegion::SummaryUsername => screenshot().select(screenshots::summary_username).first(conn)?,
+        CropRegion::MissionSummary => None,
+    };
+    let raw_text = summary.ok_or_else(|| AppError::new(ErrorCode::NotFound, format!("No recognized value for {:?} on screenshot {}", region, screenshot_id)))?;
+    let language: Option<String> = screenshot().select(screenshots::language).first(conn)?;
+
+    Ok(fields::extract_text_field(region, raw_text, language.as_deref().and_then(GameLanguage::from_code)))
+}
+
+/// Loads the correction history of a screenshot, oldest first
+pub fn get_corrections(conn: &mut DbConnection, screenshot_id: i32) -> Result<Vec<Correction>, diesel::result::Error> {
+    corrections::table
+        .filter(corrections::screenshot_id.eq(screenshot_id))
+        .order(corrections::id.asc())
+        .load(conn)
+}
+
+/// Loads the fields extracted from a screenshot
+pub fn get_match_fields(conn: &mut DbConnection, screenshot_id: i32) -> Result<Vec<MatchFieldRecord>, diesel::result::Error> {
+    match_fields::table
+        .filter(match_fields::screenshot_id.eq(screenshot_id))
+        .order(match_fields::id.asc())
+        .select(MatchFieldRecord::as_select())
+        .load(conn)
+}
+
 pub async fn save_system_info(conn: &mut DbConnection) -> Result<(), Box<dyn std::error::Error>> {
     use tauri_plugin_system_info::utils::SysInfoState;
-    use crate::models::settings::settings::dsl::*;
-    use diesel::dsl::exists;
-    use diesel::select;
     
     // Initialize system info state
     let state = SysInfoState::default();
@@ -69,26 +354,102 @@ pub async fn save_system_info(conn: &mut DbConnection) -> Result<(), Box<dyn std
 
     // Update or insert each system info setting
     for (setting_key, setting_value) in system_info {
-        let exists = select(exists(settings.filter(key.eq(setting_key.to_string())))).get_result(conn)?;
-        
-        if exists {
-            diesel::update(settings.filter(key.eq(setting_key.to_string())))
-                .set(value.eq(setting_value))
-                .execute(conn)?;
-        } else {
-            diesel::insert_into(settings)
-                .values(&Setting {
-                    id: None,
-                    key: setting_key.to_string(),
-                    value: setting_value,
-                })
-                .execute(conn)?;
-        }
+        set_setting(conn, setting_key, &setting_value)?;
+    }
+
+    Ok(())
+}
+
+/// Settings holding credentials, never shown outside the app
+const SECRET_SETTINGS: [&str; 2] = ["api_token", "webhook_urls"];
+
+/// Whether a setting holds a credential. Keys named like one count too, so a secret added
+/// later is covered even if it isn't listed.
+pub fn is_secret_setting(setting_key: &str) -> bool {
+    SECRET_SETTINGS.contains(&setting_key)
+        || ["_token", "_secret", "_password", "_api_key"].iter().any(|suffix| setting_key.ends_with(suffix))
+}
+
+/// Returns the value stored for a setting key, if present
+pub fn get_setting(conn: &mut DbConnection, setting_key: &str) -> Option<String> {
+    use crate::models::settings::settings::dsl::*;
+
+    settings
+        .filter(key.eq(setting_key))
+        .select(value)
+        .first(conn)
+        .ok()
+}
+
+/// Updates a setting, inserting it when the key does not exist yet
+pub fn set_setting(conn: &mut DbConnection, setting_key: &str, setting_value: &str) -> Result<(), diesel::result::Error> {
+    use crate::models::settings::settings::dsl::*;
+    use diesel::dsl::exists;
+    use diesel::select;
+
+    let exists = select(exists(settings.filter(key.eq(setting_key)))).get_result(conn)?;
+
+    if exists {
+        diesel::update(settings.filter(key.eq(setting_key)))
+            .set(value.eq(setting_value))
+            .execute(conn)?;
+    } else {
+        diesel::insert_into(settings)
+            .values(&Setting {
+                id: None,
+                key: setting_key.to_string(),
+                value: setting_value.to_string(),
+            })
+            .execute(conn)?;
     }
 
     Ok(())
 }
 
-fn get_db_path(app: &AppHandle) -> Result<String, Box<dyn std::error::Error>> {
+/// The connection pool held in the app state
+pub fn pool<R: Runtime>(app: &AppHandle<R>) -> AppResult<DbPool> {
+    app.state::<crate::AppState>()
+        .inner()
+        .db
+        .clone()
+        .ok_or_else(|| AppError::new(ErrorCode::DbUnavailable, "Database not initialized"))
+}
+
+/// Takes a connection from the pool. This blocks until one is free,
+/// async code should use `run` instead.
+pub fn connection<R: Runtime>(app: &AppHandle<R>) -> AppResult<PooledConnection> {
+    Ok(pool(app)?.get()?)
+}
+
+/// Runs Diesel work on a blocking thread with a pooled connection
+pub async fn run<R, T, E, F>(app: &AppHandle<R>, work: F) -> AppResult<T>
+where
+    R: Runtime,
+    T: Send + 'static,
+    E: Into<AppError>,
+    F: FnOnce(&mut DbConnection) -> Result<T, E> + Send + 'static,
+{
+    let pool = pool(app)?;
+    tokio::task::spawn_blocking(move || {
+        let mut conn = pool.get()?;
+        work(&mut conn).map_err(Into::into)
+    })
+    .await?
+}
+
+/// Reads a setting on a blocking thread, use this on async paths
+pub async fn setting<R: Runtime>(app: &AppHandle<R>, setting_key: &str) -> Option<String> {
+    let setting_key = setting_key.to_string();
+    run(app, move |conn| Ok::<_, AppError>(get_setting(conn, &setting_key))).await.ok().flatten()
+}
+
+/// Reads a setting with a pooled connection on the current thread.
+/// Only for setup and code that already runs on a blocking thread, async paths use `setting`.
+pub fn read_setting<R: Runtime>(app: &AppHandle<R>, setting_key: &str) -> Option<String> {
+    let mut conn = connection(app).ok()?;
+    get_setting(&mut conn, setting_key)
+}
+
+fn get_db_path(app: &AppHandle) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
     Ok(app.path().resolve("app.db", BaseDirectory::AppData)?.to_string_lossy().into_owned())
 }
\ No newline at end of file
@@ -0,0 +1,198 @@
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use image::{DynamicImage, Rgba, RgbaImage};
+use imageproc::drawing::draw_hollow_rect_mut;
+use imageproc::rect::Rect;
+use std::error::Error;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager, path::BaseDirectory};
+use tracing::error;
+
+use crate::crop::CropRegion;
+use crate::db;
+use crate::ocr::{OcrResult, TextRect};
+
+pub const REGION_COLOR: Rgba<u8> = Rgba([255, 64, 64, 255]);
+pub const WORD_COLOR: Rgba<u8> = Rgba([64, 255, 64, 255]);
+
+static JOB_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+/// Artifacts collected for a single cropped and recognized region
+#[derive(Debug, Clone, serde::Serialize)]
+struct RegionArtifact {
+    region: CropRegion,
+    rect: (u32, u32, u32, u32),
+    /// Capture pixels per pixel of the recognized crop, preprocessing may have upscaled it
+    scale: (f32, f32),
+    ocr: OcrResult,
+    crop_ms: u128,
+    ocr_ms: u128,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct JobReport<'a> {
+    job_id: &'a str,
+    total_ms: u128,
+    regions: &'a [RegionArtifact],
+}
+
+/// Collects debug output for one capture job and writes it to `debug_images/jobs/<job_id>/`
+pub struct DebugJob {
+    id: String,
+    dir: PathBuf,
+    started: Instant,
+    capture: Option<RgbaImage>,
+    regions: Vec<RegionArtifact>,
+}
+
+/// Returns true when per-job debug artifacts are enabled in settings
+pub async fn is_enabled(app: &AppHandle) -> bool {
+    db::setting(app, "debug_artifacts")
+        .await
+        .map(|v| v.trim().eq_ignore_ascii_case("true"))
+        .unwrap_or(false)
+}
+
+impl DebugJob {
+    /// Starts a job for the given capture when debug mode is enabled
+    pub async fn start(app: &AppHandle, base64_capture: &str) -> Option<DebugJob> {
+        if !is_enabled(app).await {
+            return None;
+        }
+
+        match Self::create(app, base64_capture) {
+            Ok(job) => Some(job),
+            Err(e) => {
+                error!("Failed to start debug job: {}", e);
+                None
+            }
+        }
+    }
+
+    fn create(app: &AppHandle, base64_capture: &str) -> Result<DebugJob, Box<dyn Error + Send + Sync>> {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+        let id = format!("{}_{}", timestamp, JOB_COUNTER.fetch_add(1, Ordering::SeqCst));
+        let dir = app.path().resolve(format!("debug_images/jobs/{}", id), BaseDirectory::AppData)?;
+        std::fs::create_dir_all(&dir)?;
+
+        // Keep the original capture exactly as it was received, in whatever format it was encoded
+        let capture_data = BASE64.decode(base64_capture)?;
+        let extension = image::guess_format(&capture_data)
+            .ok()
+            .and_then(|format| format.extensions_str().first().copied())
+            .unwrap_or("jpg");
+        std::fs::write(dir.join(format!("capture.{}", extension)), &capture_data)?;
+        let capture = image::load_from_memory(&capture_data)?.to_rgba8();
+
+        Ok(DebugJob {
+            id,
+            dir,
+            started: Instant::now(),
+            capture: Some(capture),
+            regions: Vec::new(),
+        })
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Records the processed crop and OCR output of a region cropped from `rect` of the capture
+    pub fn record_region(&mut self, region: CropRegion, rect: (u32, u32, u32, u32), crop: &DynamicImage, ocr_result: &OcrResult, crop_time: Duration, ocr_time: Duration) {
+        if let Err(e) = crop.save(self.dir.join(format!("{:?}.png", region))) {
+            error!("Failed to write debug crop for {:?}: {}", region, e);
+        }
+
+        self.regions.push(RegionArtifact {
+            region,
+            rect,
+            scale: crop_scale(rect, crop),
+            ocr: ocr_result.clone(),
+            crop_ms: crop_time.as_millis(),
+            ocr_ms: ocr_time.as_millis(),
+        });
+    }
+
+    /// Writes the annotated overlay and the JSON report for the job
+    pub fn finish(mut self) {
+        if let Err(e) = self.write_outputs() {
+            error!("Failed to write debug job {}: {}", self.id, e);
+        }
+    }
+
+    fn write_outputs(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        if let Some(mut overlay) = self.capture.take() {
+            for artifact in &self.regions {
+                let (x, y, width, height) = artifact.rect;
+                draw_box(&mut overlay, x as i32, y as i32, width, height, REGION_COLOR);
+
+                // Word boxes are relative to the preprocessed crop, map them back onto the capture
+                for word in artifact.ocr.words() {
+                    if word.rect.width > 0 && word.rect.height > 0 {
+                        let (word_x, word_y, word_width, word_height) = capture_box(artifact.rect, artifact.scale, word.rect);
+                        draw_box(&mut overlay, word_x, word_y, word_width, word_height, WORD_COLOR);
+                    }
+                }
+            }
+            overlay.save(self.dir.join("overlay.png"))?;
+        }
+
+        let report = JobReport {
+            job_id: &self.id,
+            total_ms: self.started.elapsed().as_millis(),
+            regions: &self.regions,
+        };
+        std::fs::write(self.dir.join("result.json"), serde_json::to_string_pretty(&report)?)?;
+
+        Ok(())
+    }
+}
+
+/// Ratio between the region rectangle on the capture and the image that was recognized
+fn crop_scale(rect: (u32, u32, u32, u32), crop: &DynamicImage) -> (f32, f32) {
+    let (_, _, width, height) = rect;
+    (width as f32 / crop.width().max(1) as f32, height as f32 / crop.height().max(1) as f32)
+}
+
+/// Moves a box found in the recognized crop onto the capture
+fn capture_box(rect: (u32, u32, u32, u32), scale: (f32, f32), word: TextRect) -> (i32, i32, u32, u32) {
+    let (x, y, _, _) = rect;
+    let (scale_x, scale_y) = scale;
+    (
+        x as i32 + (word.x as f32 * scale_x).round() as i32,
+        y as i32 + (word.y as f32 * scale_y).round() as i32,
+        (word.width as f32 * scale_x).round() as u32,
+        (word.height as f32 * scale_y).round() as u32,
+    )
+}
+
+/// Draws a two pixel wide rectangle outline
+pub fn draw_box(image: &mut RgbaImage, x: i32, y: i32, width: u32, height: u32, color: Rgba<u8>) {
+    if width == 0 || height == 0 {
+        return;
+    }
+
+    draw_hollow_rect_mut(image, Rect::at(x, y).of_size(width, height), color);
+    if width > 2 && height > 2 {
+        draw_hollow_rect_mut(image, Rect::at(x + 1, y + 1).of_size(width - 2, height - 2), color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::preprocess::{self, PreprocessStep};
+
+    #[test]
+    fn test_word_boxes_undo_upscaling() {
+        let rect = (300, 200, 100, 20);
+        let crop = DynamicImage::ImageRgba8(RgbaImage::from_pixel(100, 20, Rgba([255, 255, 255, 255])));
+        let crop = preprocess::apply_all(crop, &[PreprocessStep::Grayscale, PreprocessStep::Upscale { factor: 2.0 }]);
+        assert_eq!((crop.width(), crop.height()), (200, 40));
+
+        let scale = crop_scale(rect, &crop);
+        let word = TextRect { x: 40, y: 10, width: 60, height: 20 };
+        assert_eq!(capture_box(rect, scale, word), (320, 205, 30, 10));
+    }
+}
@@ -0,0 +1,324 @@
+use diesel::prelude::*;
+use image::DynamicImage;
+use std::error::Error;
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager, path::BaseDirectory};
+use tracing::{info, warn};
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+use crate::calibration::{self, Calibration};
+use crate::crop::CropRegion;
+use crate::localize::RegionLayout;
+use crate::models::{match_fields, screenshots};
+use crate::privacy::{Privacy, PrivacyMode, RedactArea, RedactStyle};
+use crate::retention::{self, StorageCategory};
+use crate::{ai_models, logging};
+
+/// Number of debug jobs and full captures copied into a bundle
+const RECENT_CAPTURES: usize = 5;
+
+const REDACTED: &str = "[redacted]";
+
+/// Summary written as `bundle.json` at the root of the archive
+#[derive(Debug, serde::Serialize)]
+struct BundleInfo {
+    app_version: String,
+    created_at: String,
+    redacted: bool,
+    files: Vec<String>,
+}
+
+/// Packages logs, settings, system info, model status and the latest debug captures into a zip.
+/// With `redact`, player names and the OS account name are removed from text and
+/// the username card is blacked out in every image, which also happens when `privacy` is active.
+pub fn create_bundle(app: &AppHandle, redact: bool, privacy: &Privacy) -> Result<PathBuf, Box<dyn Error + Send + Sync>> {
+    let dir = app.path().resolve("diagnostics", BaseDirectory::AppData)?;
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("martini_diagnostics_{}.zip", chrono::Local::now().format("%Y%m%d_%H%M%S")));
+
+    // Captures taken before privacy redaction was turned on still show names
+    let redact_images = redact || privacy.is_active();
+    // Settings include the system info gathered at startup
+    let settings = load_settings(app)?;
+    let redactor = Redactor::new(if redact { known_usernames(app) } else { Vec::new() }, redact_images, calibration::saved(&settings));
+    let mut bundle = Bundle {
+        zip: ZipWriter::new(std::fs::File::create(&path)?),
+        redactor,
+        files: Vec::new(),
+    };
+
+    bundle.add_settings(&settings)?;
+
+    match ai_models::verify_models(app) {
+        Ok(statuses) => bundle.add_text("models.json", &serde_json::to_string_pretty(&statuses)?)?,
+        Err(e) => bundle.add_text("models.json", &format!("Failed to verify models: {}", e))?,
+    }
+
+    let log_dir = logging::log_dir(app)?;
+    if log_dir.exists() {
+        for entry in std::fs::read_dir(&log_dir)? {
+            let path = entry?.path();
+            if path.is_file() {
+                bundle.add_text_file(&format!("logs/{}", file_name(&path)), &path)?;
+            }
+        }
+    }
+
+    for capture in retention::recent_files(app, StorageCategory::Captures, RECENT_CAPTURES)? {
+        bundle.add_image(&format!("captures/{}", file_name(&capture)), &capture, true)?;
+    }
+
+    for job in retention::recent_files(app, StorageCategory::Jobs, RECENT_CAPTURES)? {
+        let job_name = file_name(&job);
+        for entry in std::fs::read_dir(&job)? {
+            let file = entry?.path();
+            let name = file_name(&file);
+            let archive_name = format!("jobs/{}/{}", job_name, name);
+
+            match file.extension().and_then(|e| e.to_str()) {
+                Some("json") => bundle.add_text_file(&archive_name, &file)?,
+                // The username crop shows nothing but the name
+                Some("png") if redact && name == format!("{:?}.png", CropRegion::SummaryUsername) => continue,
+                Some("png") | Some("jpg") | Some("webp") => {
+                    let is_capture = name.starts_with("capture.") || name == "overlay.png";
+                    bundle.add_image(&archive_name, &file, is_capture)?
+                }
+                _ => continue,
+            }
+        }
+    }
+
+    bundle.finish(redact)?;
+    info!("Diagnostics bundle written to {}", path.display());
+    Ok(path)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default()
+}
+
+fn load_settings(app: &AppHandle) -> Result<Vec<(String, String)>, Box<dyn Error + Send + Sync>> {
+    use crate::models::settings::settings::dsl::*;
+
+    let mut conn = crate::db::connection(app)?;
+    Ok(settings.select((key, value)).order(key.asc()).load(&mut *conn)?)
+}
+
+/// Player names seen in stored matches, plus the OS account name that shows up in paths
+fn known_usernames(app: &AppHandle) -> Vec<String> {
+    let mut names = Vec::new();
+
+    match crate::db::connection(app) {
+        Ok(mut conn) => {
+            let stored: Vec<Option<String>> = screenshots::table
+                .select(screenshots::summary_username)
+                .distinct()
+                .load(&mut *conn)
+                .unwrap_or_default();
+            let region = format!("{:?}", CropRegion::SummaryUsername);
+            let fields: Vec<(String, Option<String>)> = match_fields::table
+                .filter(match_fields::region.eq(region))
+                .select((match_fields::value, match_fields::manual_value))
+                .load(&mut *conn)
+                .unwrap_or_default();
+
+            names.extend(stored.into_iter().flatten());
+            for (value, manual_value) in fields {
+                names.push(value);
+                names.extend(manual_value);
+            }
+        }
+        Err(e) => warn!("Failed to load usernames for redaction: {}", e),
+    }
+
+    for variable in ["USERNAME", "USER"] {
+        if let Ok(name) = std::env::var(variable) {
+            names.push(name);
+        }
+    }
+
+    names
+}
+
+/// Replaces known names in text and hides the username card in images
+struct Redactor {
+    names: Vec<String>,
+    images: bool,
+    /// Saved region calibrations, so the card is hidden where it is on calibrated resolutions
+    calibrations: Vec<Calibration>,
+}
+
+impl Redactor {
+    fn new(names: Vec<String>, images: bool, calibrations: Vec<Calibration>) -> Self {
+        let mut names: Vec<String> = names
+            .into_iter()
+            .map(|name| name.trim().to_string())
+            // Very short values are mostly OCR noise and would mangle unrelated text
+            .filter(|name| name.chars().count() >= 3)
+            .collect();
+        // Replace longer names first so a name containing another is removed whole
+        names.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+        names.dedup();
+        Redactor { names, images, calibrations }
+    }
+
+    fn text(&self, text: &str) -> String {
+        self.names.iter().fold(text.to_string(), |current, name| current.replace(name.as_str(), REDACTED))
+    }
+
+    fn image(&self, img: DynamicImage) -> DynamicImage {
+        if !self.images {
+            return img;
+        }
+
+        let layout = self
+            .calibrations
+            .iter()
+            .find(|calibration| calibration.width == img.width() && calibration.height == img.height())
+            .map(Calibration::layout)
+            .unwrap_or_else(RegionLayout::fixed);
+        let username_card = Privacy {
+            mode: PrivacyMode::Redact,
+            style: RedactStyle::Black,
+            areas: vec![RedactArea::Region(CropRegion::SummaryUsername)],
+        };
+        username_card.apply(&img, &layout)
+    }
+}
+
+struct Bundle {
+    zip: ZipWriter<std::fs::File>,
+    redactor: Redactor,
+    files: Vec<String>,
+}
+
+impl Bundle {
+    fn add_bytes(&mut self, name: &str, data: &[u8]) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.zip.start_file(name, SimpleFileOptions::default())?;
+        self.zip.write_all(data)?;
+        self.files.push(name.to_string());
+        Ok(())
+    }
+
+    fn add_text(&mut self, name: &str, text: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let text = self.redactor.text(text);
+        self.add_bytes(name, text.as_bytes())
+    }
+
+    /// Adds `settings.json`, credentials are always replaced whether or not the bundle is redacted
+    fn add_settings(&mut self, settings: &[(String, String)]) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let settings: Vec<(&str, &str)> = settings
+            .iter()
+            .map(|(key, value)| (key.as_str(), if crate::db::is_secret_setting(key) && !value.is_empty() { REDACTED } else { value.as_str() }))
+            .collect();
+        self.add_text("settings.json", &serde_json::to_string_pretty(&settings)?)
+    }
+
+    fn add_text_file(&mut self, name: &str, path: &Path) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let text = String::from_utf8_lossy(&std::fs::read(path)?).into_owned();
+        self.add_text(name, &text)
+    }
+
+    /// Adds an image, full captures get the username card blacked out when redacting
+    fn add_image(&mut self, name: &str, path: &Path, full_capture: bool) -> Result<(), Box<dyn Error + Send + Sync>> {
+        if !(full_capture && self.redactor.images) {
+            let data = std::fs::read(path)?;
+            return self.add_bytes(name, &data);
+        }
+
+        let img = self.redactor.image(image::open(path)?);
+        let mut buffer = Cursor::new(Vec::new());
+        img.write_to(&mut buffer, image::ImageFormat::Png)?;
+        let name = Path::new(name).with_extension("png").to_string_lossy().into_owned();
+        self.add_bytes(&name, &buffer.into_inner())
+    }
+
+    fn finish(mut self, redacted: bool) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let info = BundleInfo {
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            created_at: chrono::Local::now().to_rfc3339(),
+            redacted,
+            files: self.files.clone(),
+        };
+        let json = serde_json::to_string_pretty(&info)?;
+        self.add_bytes("bundle.json", json.as_bytes())?;
+        self.zip.finish()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crop;
+    use image::Rgba;
+
+    #[test]
+    fn test_redactor_replaces_longest_names_first() {
+        let redactor = Redactor::new(vec!["Hunter".to_string(), "Hunter42".to_string(), "ab".to_string(), " Hunter ".to_string()], true, Vec::new());
+
+        assert_eq!(redactor.names, vec!["Hunter42", "Hunter"]);
+        assert_eq!(
+            redactor.text(r#"C:\Users\Hunter\AppData "Hunter42" ab"#),
+            r#"C:\Users\[redacted]\AppData "[redacted]" ab"#
+        );
+    }
+
+    #[test]
+    fn test_bundle_never_contains_secrets() {
+        let path = std::env::temp_dir().join(format!("martini_bundle_{}.zip", std::process::id()));
+        let mut bundle = Bundle {
+            zip: ZipWriter::new(std::fs::File::create(&path).unwrap()),
+            redactor: Redactor::new(Vec::new(), false, Vec::new()),
+            files: Vec::new(),
+        };
+        let settings = vec![
+            ("api_token".to_string(), "s3cr3tT0ken".to_string()),
+            ("webhook_urls".to_string(), "https://discord.com/api/webhooks/1/hookSecret".to_string()),
+            ("obs_password".to_string(), "hunter2".to_string()),
+            ("capture_format".to_string(), "png".to_string()),
+        ];
+        bundle.add_settings(&settings).unwrap();
+        bundle.finish(false).unwrap();
+
+        let mut archive = zip::ZipArchive::new(std::fs::File::open(&path).unwrap()).unwrap();
+        let mut json = String::new();
+        std::io::Read::read_to_string(&mut archive.by_name("settings.json").unwrap(), &mut json).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        for secret in ["s3cr3tT0ken", "hookSecret", "hunter2"] {
+            assert!(!json.contains(secret), "{} leaked into the bundle", secret);
+        }
+        assert!(json.contains(REDACTED));
+        assert!(json.contains("png"));
+    }
+
+    #[test]
+    fn test_redactor_blacks_out_username_card() {
+        let redactor = Redactor::new(Vec::new(), true, Vec::new());
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(1920, 1080, Rgba([255, 255, 255, 255])));
+
+        let (x, y, _, _) = crop::region_rect(1920, 1080, CropRegion::SummaryUsername);
+        let redacted = redactor.image(img).to_rgba8();
+
+        assert_eq!(redacted.get_pixel(x, y), &Rgba([0, 0, 0, 255]));
+        assert_eq!(redacted.get_pixel(0, 0), &Rgba([255, 255, 255, 255]));
+    }
+
+    #[test]
+    fn test_redactor_follows_saved_calibration() {
+        let calibration = Calibration { width: 1920, height: 1080, scale_x: 1.0, scale_y: 1.0, offset_x: 0.0, offset_y: 300.0 };
+        let settings = vec![("region_calibration_1920x1080".to_string(), serde_json::to_string(&calibration).unwrap())];
+        let redactor = Redactor::new(Vec::new(), true, calibration::saved(&settings));
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(1920, 1080, Rgba([255, 255, 255, 255])));
+
+        let (x, y, width, height) = calibration.rect(CropRegion::SummaryUsername);
+        let (fixed_x, fixed_y, _, _) = crop::region_rect(1920, 1080, CropRegion::SummaryUsername);
+        let redacted = redactor.image(img).to_rgba8();
+
+        assert_eq!(redacted.get_pixel(x + width / 2, y + height / 2), &Rgba([0, 0, 0, 255]));
+        assert_eq!(redacted.get_pixel(fixed_x, fixed_y), &Rgba([255, 255, 255, 255]));
+    }
+}
@@ -0,0 +1,306 @@
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::{DynamicImage, ImageError, ImageFormat};
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+use tauri::{AppHandle, Manager, Runtime, path::BaseDirectory};
+use tracing::{info, warn};
+
+use crate::crop::{self, CropRegion};
+use crate::db;
+use crate::error::{AppError, ErrorCode};
+use crate::fields;
+use crate::labels;
+use crate::localize;
+use crate::pipeline;
+
+/// Name of the file listing the expected values of every corpus image
+const EXPECTED_FILE: &str = "expected.json";
+
+/// How captures and crops are encoded, from the `capture_format` setting.
+/// Accepts `png`, `webp` or `jpeg:<quality>` such as `jpeg:90`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "format", rename_all = "snake_case")]
+pub enum CaptureFormat {
+    /// Lossless
+    Png,
+    /// Lossless WebP, smaller than PNG but slower to encode
+    Webp,
+    /// Lossy, quality from 1 to 100
+    Jpeg { quality: u8 },
+}
+
+impl Default for CaptureFormat {
+    /// Captures were always JPEG at quality 100 before the setting existed
+    fn default() -> Self {
+        CaptureFormat::Jpeg { quality: 100 }
+    }
+}
+
+impl CaptureFormat {
+    /// Formats compared by `compare_formats`
+    pub const CANDIDATES: [CaptureFormat; 6] = [
+        CaptureFormat::Png,
+        CaptureFormat::Webp,
+        CaptureFormat::Jpeg { quality: 100 },
+        CaptureFormat::Jpeg { quality: 90 },
+        CaptureFormat::Jpeg { quality: 85 },
+        CaptureFormat::Jpeg { quality: 75 },
+    ];
+
+    pub fn parse(value: &str) -> Option<CaptureFormat> {
+        let value = value.trim().to_lowercase();
+        let (name, quality) = match value.split_once(':') {
+            Some((name, quality)) => (name.trim(), Some(quality.trim())),
+            None => (value.as_str(), None),
+        };
+
+        match (name, quality) {
+            ("png", None) => Some(CaptureFormat::Png),
+            ("webp", None) => Some(CaptureFormat::Webp),
+            ("jpeg" | "jpg", None) => Some(CaptureFormat::default()),
+            ("jpeg" | "jpg", Some(quality)) => match quality.parse::<u8>() {
+                Ok(quality @ 1..=100) => Some(CaptureFormat::Jpeg { quality }),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// The setting value of the format, also stored in `screenshots.image_format`
+    pub fn name(&self) -> String {
+        match self {
+            CaptureFormat::Png => "png".to_string(),
+            CaptureFormat::Webp => "webp".to_string(),
+            CaptureFormat::Jpeg { quality } => format!("jpeg:{}", quality),
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            CaptureFormat::Png => "png",
+            CaptureFormat::Webp => "webp",
+            CaptureFormat::Jpeg { .. } => "jpg",
+        }
+    }
+
+    pub fn image_format(&self) -> ImageFormat {
+        match self {
+            CaptureFormat::Png => ImageFormat::Png,
+            CaptureFormat::Webp => ImageFormat::WebP,
+            CaptureFormat::Jpeg { .. } => ImageFormat::Jpeg,
+        }
+    }
+
+    pub fn is_lossless(&self) -> bool {
+        !matches!(self, CaptureFormat::Jpeg { .. })
+    }
+
+    pub fn encode(&self, img: &DynamicImage) -> Result<Vec<u8>, ImageError> {
+        let mut buffer = Cursor::new(Vec::new());
+        match self {
+            CaptureFormat::Png => img.write_to(&mut buffer, ImageFormat::Png)?,
+            CaptureFormat::Webp => img.write_with_encoder(WebPEncoder::new_lossless(&mut buffer))?,
+            // JPEG has no alpha channel
+            CaptureFormat::Jpeg { quality } => JpegEncoder::new_with_quality(&mut buffer, *quality).encode_image(&img.to_rgb8())?,
+        }
+        Ok(buffer.into_inner())
+    }
+}
+
+/// The configured capture format, JPEG at quality 100 when unset or invalid
+pub async fn configured<R: Runtime>(app: &AppHandle<R>) -> CaptureFormat {
+    match db::setting(app, "capture_format").await {
+        Some(value) => CaptureFormat::parse(&value).unwrap_or_else(|| {
+            warn!("Invalid capture_format setting {:?}, using JPEG", value);
+            CaptureFormat::default()
+        }),
+        None => CaptureFormat::default(),
+    }
+}
+
+/// Decodes image data in a known format, or guesses it from the data when unknown
+pub fn decode(data: &[u8], format: Option<CaptureFormat>) -> Result<DynamicImage, ImageError> {
+    match format {
+        Some(format) => image::load_from_memory_with_format(data, format.image_format()),
+        None => image::load_from_memory(data),
+    }
+}
+
+/// OCR accuracy and storage cost of one format over the corpus
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct FormatResult {
+    pub format: String,
+    pub lossless: bool,
+    pub fields: usize,
+    pub correct: usize,
+    pub accuracy: f64,
+    /// Encoded size of all corpus images
+    pub bytes: u64,
+    /// Size once base64 encoded, which is how captures are stored in the database
+    pub stored_bytes: u64,
+    pub encode_ms: u128,
+    pub mismatches: Vec<FieldMismatch>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct FieldMismatch {
+    pub image: String,
+    pub region: CropRegion,
+    pub expected: String,
+    pub actual: String,
+}
+
+/// Comparison of every candidate format, written to `reports/formats_<ts>.json`
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct FormatReport {
+    pub corpus: String,
+    pub images: usize,
+    pub formats: Vec<FormatResult>,
+    pub path: Option<String>,
+}
+
+/// Expected values of a corpus image, keyed by region name such as `SummaryFirst`
+type ExpectedFields = HashMap<String, String>;
+
+/// Default corpus location, `AppData/corpus`
+pub fn corpus_dir(app: &AppHandle) -> Result<PathBuf, Box<dyn Error + Send + Sync>> {
+    Ok(app.path().resolve("corpus", BaseDirectory::AppData)?)
+}
+
+/// Re-encodes every corpus image in each candidate format, runs the OCR pipeline on the
+/// decoded result and scores it against `expected.json`.
+/// The corpus images should be lossless masters so every format starts from the same pixels.
+pub async fn compare_formats(app: &AppHandle, corpus: &Path) -> Result<FormatReport, Box<dyn Error + Send + Sync>> {
+    let expected_path = corpus.join(EXPECTED_FILE);
+    let expected: HashMap<String, ExpectedFields> = match std::fs::read_to_string(&expected_path) {
+        Ok(json) => serde_json::from_str(&json)
+            .map_err(|e| AppError::new(ErrorCode::InvalidInput, format!("Invalid {}: {}", expected_path.display(), e)))?,
+        Err(e) => return Err(AppError::new(ErrorCode::NotFound, format!("Failed to read {}: {}", expected_path.display(), e)).into()),
+    };
+
+    let mut names: Vec<&String> = expected.keys().collect();
+    names.sort();
+    let language = labels::configured_language(app).await;
+
+    let mut images = Vec::new();
+    for name in names {
+        let fields = &expected[name];
+        let regions = fields
+            .keys()
+            .map(|region| {
+                crop::region_from_name(region)
+                    .ok_or_else(|| AppError::new(ErrorCode::InvalidInput, format!("Unknown region {} for {}", region, name)))
+            })
+            .collect::<Result<Vec<CropRegion>, AppError>>()?;
+        let original = image::open(corpus.join(name))?;
+        images.push((name.clone(), original, regions));
+    }
+
+    let mut formats = Vec::new();
+    for format in CaptureFormat::CANDIDATES {
+        let mut result = FormatResult {
+            format: format.name(),
+            lossless: format.is_lossless(),
+            fields: 0,
+            correct: 0,
+            accuracy: 0.0,
+            bytes: 0,
+            stored_bytes: 0,
+            encode_ms: 0,
+            mismatches: Vec::new(),
+        };
+
+        for (name, original, regions) in &images {
+            let encode_start = Instant::now();
+            let data = format.encode(original)?;
+            result.encode_ms += encode_start.elapsed().as_millis();
+            result.bytes += data.len() as u64;
+            result.stored_bytes += BASE64.encode(&data).len() as u64;
+
+            let decoded = Arc::new(decode(&data, Some(format))?);
+            let layout = Arc::new(localize::locate(app, decoded.clone()).await);
+            let outcomes = pipeline::process_decoded_regions(app, decoded, layout, regions).await?;
+
+            for outcome in outcomes {
+                let expected_value = &expected[name][&format!("{:?}", outcome.region)];
+                let field = fields::extract_field(outcome.region, &outcome.result, language.or_else(|| labels::detect_language(&outcome.result.text())));
+                result.fields += 1;
+                if field_matches(&field, expected_value) {
+                    result.correct += 1;
+                } else {
+                    result.mismatches.push(FieldMismatch {
+                        image: name.clone(),
+                        region: outcome.region,
+                        expected: expected_value.clone(),
+                        actual: field.value,
+                    });
+                }
+            }
+        }
+
+        if result.fields > 0 {
+            result.accuracy = result.correct as f64 / result.fields as f64;
+        }
+        info!("Format {}: {}/{} fields correct, {} bytes", result.format, result.correct, result.fields, result.bytes);
+        formats.push(result);
+    }
+
+    let mut report = FormatReport {
+        corpus: corpus.to_string_lossy().into_owned(),
+        images: images.len(),
+        formats,
+        path: None,
+    };
+    let dir = app.path().resolve("reports", BaseDirectory::AppData)?;
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("formats_{}.json", chrono::Local::now().format("%Y%m%d_%H%M%S")));
+    std::fs::write(&path, serde_json::to_string_pretty(&report)?)?;
+    report.path = Some(path.to_string_lossy().into_owned());
+
+    Ok(report)
+}
+
+/// Numeric regions compare the parsed number, text regions the folded value
+fn field_matches(field: &fields::ExtractedField, expected: &str) -> bool {
+    if field.region.is_numeric() {
+        let digits: String = expected.chars().filter(|c| c.is_ascii_digit() || *c == '-').collect();
+        return digits.parse::<i64>().ok().is_some_and(|number| field.number == Some(number));
+    }
+    labels::normalize(&field.value) == labels::normalize(expected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_capture_format() {
+        assert_eq!(CaptureFormat::parse("PNG"), Some(CaptureFormat::Png));
+        assert_eq!(CaptureFormat::parse("webp"), Some(CaptureFormat::Webp));
+        assert_eq!(CaptureFormat::parse("jpeg:85"), Some(CaptureFormat::Jpeg { quality: 85 }));
+        assert_eq!(CaptureFormat::parse("jpg"), Some(CaptureFormat::Jpeg { quality: 100 }));
+        assert_eq!(CaptureFormat::parse("jpeg:0"), None);
+        assert_eq!(CaptureFormat::parse("png:90"), None);
+        assert_eq!(CaptureFormat::parse(&CaptureFormat::Jpeg { quality: 90 }.name()), Some(CaptureFormat::Jpeg { quality: 90 }));
+    }
+
+    #[test]
+    fn test_lossless_formats_round_trip() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::from_fn(64, 32, |x, y| image::Rgba([(x * 4) as u8, (y * 8) as u8, 128, 255])));
+
+        for format in [CaptureFormat::Png, CaptureFormat::Webp] {
+            let data = format.encode(&img).unwrap();
+            assert_eq!(decode(&data, Some(format)).unwrap().to_rgba8(), img.to_rgba8());
+        }
+
+        let jpeg = CaptureFormat::Jpeg { quality: 75 }.encode(&img).unwrap();
+        let decoded = decode(&jpeg, None).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (64, 32));
+    }
+}
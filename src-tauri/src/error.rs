@@ -0,0 +1,145 @@
+use std::error::Error;
+use std::fmt;
+
+/// Stable error codes the frontend can match on to show a specific message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// The game window was found but could not be captured
+    CaptureFailed,
+    /// No window matched the configured titles
+    WindowNotFound,
+    /// An OCR model file is missing
+    ModelMissing,
+    /// An OCR model file failed size or checksum verification
+    ModelInvalid,
+    /// Text detection or recognition failed
+    OcrFailed,
+    /// A capture or crop could not be decoded or encoded
+    ImageFailed,
+    /// The database connection is not available
+    DbUnavailable,
+    /// The database is busy or no pooled connection is free
+    DbLocked,
+    /// A query failed
+    DbFailed,
+    /// The requested row does not exist
+    NotFound,
+    /// A command argument or stored value is invalid
+    InvalidInput,
+    /// Another job of the same kind is already running
+    Busy,
+    /// Reading or writing a file failed
+    Io,
+    /// A local API request had no token or a wrong one
+    Unauthorized,
+    Internal,
+}
+
+/// Error returned by every command, serialized as `{ code, message }`
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct AppError {
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+pub type AppResult<T> = Result<T, AppError>;
+
+impl AppError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        AppError { code, message: message.into() }
+    }
+
+    /// Prefixes the message with what was being done, keeping the code
+    pub fn context(self, context: impl fmt::Display) -> Self {
+        AppError { code: self.code, message: format!("{}: {}", context, self.message) }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for AppError {}
+
+impl From<diesel::result::Error> for AppError {
+    fn from(e: diesel::result::Error) -> Self {
+        use diesel::result::{DatabaseErrorKind, Error as DieselError};
+
+        let code = match &e {
+            DieselError::NotFound => ErrorCode::NotFound,
+            DieselError::DatabaseError(DatabaseErrorKind::Unknown, info) if info.message().contains("locked") || info.message().contains("busy") => ErrorCode::DbLocked,
+            _ => ErrorCode::DbFailed,
+        };
+        AppError::new(code, e.to_string())
+    }
+}
+
+impl From<image::ImageError> for AppError {
+    fn from(e: image::ImageError) -> Self {
+        AppError::new(ErrorCode::ImageFailed, e.to_string())
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(e: std::io::Error) -> Self {
+        AppError::new(ErrorCode::Io, e.to_string())
+    }
+}
+
+impl From<tokio::task::JoinError> for AppError {
+    fn from(e: tokio::task::JoinError) -> Self {
+        AppError::new(ErrorCode::Internal, e.to_string())
+    }
+}
+
+/// No pooled connection became free before the pool's timeout
+impl From<diesel::r2d2::PoolError> for AppError {
+    fn from(e: diesel::r2d2::PoolError) -> Self {
+        AppError::new(ErrorCode::DbLocked, format!("Failed to get a database connection: {}", e))
+    }
+}
+
+/// Internals return boxed errors, an `AppError` inside the box keeps its code
+impl From<Box<dyn Error + Send + Sync>> for AppError {
+    fn from(e: Box<dyn Error + Send + Sync>) -> Self {
+        match e.downcast::<AppError>() {
+            Ok(app_error) => *app_error,
+            Err(e) => match e.downcast::<diesel::result::Error>() {
+                Ok(db_error) => AppError::from(*db_error),
+                Err(e) => match e.downcast::<image::ImageError>() {
+                    Ok(image_error) => AppError::from(*image_error),
+                    Err(e) => match e.downcast::<std::io::Error>() {
+                        Ok(io_error) => AppError::from(*io_error),
+                        Err(e) => AppError::new(ErrorCode::Internal, e.to_string()),
+                    },
+                },
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_boxed_error_keeps_code() {
+        let boxed: Box<dyn Error + Send + Sync> = AppError::new(ErrorCode::WindowNotFound, "No matching window found").into();
+        let error = AppError::from(boxed).context("Failed to capture screenshot");
+
+        assert_eq!(error.code, ErrorCode::WindowNotFound);
+        assert_eq!(error.message, "Failed to capture screenshot: No matching window found");
+        assert_eq!(
+            serde_json::to_string(&error).unwrap(),
+            r#"{"code":"window_not_found","message":"Failed to capture screenshot: No matching window found"}"#
+        );
+    }
+
+    #[test]
+    fn test_diesel_not_found() {
+        assert_eq!(AppError::from(diesel::result::Error::NotFound).code, ErrorCode::NotFound);
+    }
+}
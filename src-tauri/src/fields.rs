@@ -0,0 +1,265 @@
+use crate::crop::CropRegion;
+use crate::labels::{self, GameLanguage, Label};
+use crate::ocr::OcrResult;
+
+/// A value read from one region of the summary screen
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ExtractedField {
+    pub region: CropRegion,
+    /// The card heading found in the region, `None` for the username or unrecognized cards
+    pub label: Option<Label>,
+    /// The full OCR text of the region
+    pub raw_text: String,
+    /// The text that remains once the heading is removed
+    pub value: String,
+    /// The parsed number for numeric regions
+    pub number: Option<i64>,
+    /// Set when OCR confusions had to be corrected to read the number
+    pub corrected: bool,
+    /// Set when a numeric value is missing, malformed or outside the expected range
+    pub suspicious: bool,
+}
+
+/// Result of reading a number out of OCR text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NumericValue {
+    pub number: Option<i64>,
+    pub corrected: bool,
+    pub suspicious: bool,
+}
+
+/// Splits a region's OCR text into its card heading and value, parsing numeric cards
+pub fn extract_field(region: CropRegion, result: &OcrResult, language: Option<GameLanguage>) -> ExtractedField {
+    extract_text_field(region, result.text(), language)
+}
+
+/// Same as `extract_field` for text that was already flattened, such as the `summary_*` columns
+pub fn extract_text_field(region: CropRegion, raw_text: String, language: Option<GameLanguage>) -> ExtractedField {
+    let (label, value) = split_label(region, &raw_text, language);
+
+    let numeric = if region.is_numeric() {
+        correct_number(&value, label)
+    } else {
+        NumericValue { number: None, corrected: false, suspicious: false }
+    };
+
+    ExtractedField {
+        region,
+        label,
+        raw_text,
+        value,
+        number: numeric.number,
+        corrected: numeric.corrected,
+        suspicious: numeric.suspicious,
+    }
+}
+
+fn split_label(region: CropRegion, raw_text: &str, language: Option<GameLanguage>) -> (Option<Label>, String) {
+    if !region.is_numeric() {
+        return (None, raw_text.trim().to_string());
+    }
+
+    // Without a known language try every dictionary
+    let languages: Vec<GameLanguage> = match language {
+        Some(language) => vec![language],
+        None => GameLanguage::ALL.to_vec(),
+    };
+
+    for language in languages {
+        for label in Label::CARDS {
+            if let Some(spelling) = language.find_label(label, raw_text) {
+                return (Some(label), strip_heading(raw_text, spelling));
+            }
+        }
+    }
+
+    (None, raw_text.trim().to_string())
+}
+
+/// Removes the heading from the raw text, keeping the value's original case so
+/// upper case confusions such as `D` or `Q` for `0` can still be corrected
+fn strip_heading(raw_text: &str, spelling: &str) -> String {
+    let spans = labels::normalize_spans(raw_text);
+    let folded: String = spans.iter().map(|(c, _)| c).collect();
+
+    let value = match folded.find(spelling) {
+        Some(index) => {
+            let first = folded[..index].chars().count();
+            let last = first + spelling.chars().count() - 1;
+            format!("{} {}", &raw_text[..spans[first].1.start], &raw_text[spans[last].1.end..])
+        }
+        None => raw_text.to_string(),
+    };
+    value.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Plausible values for each card, anything outside is flagged for review
+fn expected_range(label: Option<Label>) -> std::ops::RangeInclusive<i64> {
+    match label {
+        Some(Label::BountyCollected) => 0..=2,
+        Some(Label::RiftsClosed) => 0..=3,
+        Some(Label::HuntersKilled) => 0..=11,
+        Some(Label::Extracted) => 0..=1,
+        Some(Label::MonstersKilled) => 0..=1000,
+        Some(Label::MissionSummary) | None => 0..=1_000_000,
+    }
+}
+
+/// Characters OCR commonly returns in place of digits
+fn confusable_digit(c: char) -> Option<char> {
+    match c {
+        'O' | 'o' | 'D' | 'Q' => Some('0'),
+        'l' | 'I' | 'i' | '|' | '!' => Some('1'),
+        'Z' | 'z' => Some('2'),
+        'S' | 's' | '$' => Some('5'),
+        'B' | 'b' => Some('8'),
+        _ => None,
+    }
+}
+
+fn is_separator(c: char) -> bool {
+    matches!(c, ',' | '.' | '\'' | '’' | '\u{a0}' | '\u{202f}')
+}
+
+/// Reads the number out of a card value, fixing letter/digit confusions and
+/// thousands separators such as `1,250`, `1.250`, `1 250` or `1'250`
+pub fn correct_number(value: &str, label: Option<Label>) -> NumericValue {
+    let mut corrected = false;
+
+    // Map every token to digits where possible, keeping only tokens that then look numeric
+    let tokens: Vec<(String, bool, bool)> = value
+        .split_whitespace()
+        .map(|token| {
+            let mut was_corrected = false;
+            let mapped: String = token
+                .chars()
+                .map(|c| match confusable_digit(c) {
+                    Some(digit) => {
+                        was_corrected = true;
+                        digit
+                    }
+                    None => c,
+                })
+                .collect();
+            (mapped, was_corrected, token.chars().any(|c| c.is_ascii_digit()))
+        })
+        .collect();
+
+    let is_numeric_token = |token: &str| {
+        token.chars().any(|c| c.is_ascii_digit()) && token.chars().all(|c| c.is_ascii_digit() || is_separator(c))
+    };
+
+    // Prefer tokens OCR already read as digits over words made only of look-alike letters,
+    // then join following three digit groups split by spaces
+    let start = tokens
+        .iter()
+        .position(|(token, _, had_digit)| *had_digit && is_numeric_token(token))
+        .or_else(|| tokens.iter().position(|(token, _, _)| is_numeric_token(token)));
+    let Some(start) = start else {
+        return NumericValue { number: None, corrected: false, suspicious: true };
+    };
+    // A lone letter is a common misread of a single digit, but a longer token without any
+    // real digit is most likely a word such as "is" or "Oil"
+    let (first, first_corrected, first_had_digit) = &tokens[start];
+    let word_only = !first_had_digit && first.chars().count() > 1;
+    let mut number_text = first.clone();
+    corrected |= *first_corrected;
+    for (token, was_corrected, _) in tokens.iter().skip(start + 1) {
+        if token.len() == 3 && token.chars().all(|c| c.is_ascii_digit()) {
+            number_text.push(',');
+            number_text.push_str(token);
+            corrected |= *was_corrected;
+        } else {
+            break;
+        }
+    }
+
+    // Every group after a separator must have three digits to be a thousands separator
+    let groups: Vec<&str> = number_text.split(is_separator).filter(|group| !group.is_empty()).collect();
+    let well_formed = groups.iter().skip(1).all(|group| group.len() == 3);
+
+    let number = groups.concat().parse::<i64>().ok();
+    let in_range = number.is_some_and(|n| expected_range(label).contains(&n));
+
+    NumericValue {
+        number,
+        corrected,
+        suspicious: word_only || !well_formed || !in_range,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ocr::{OcrLine, TextRect};
+
+    fn result(lines: &[&str]) -> OcrResult {
+        OcrResult {
+            lines: lines
+                .iter()
+                .map(|text| OcrLine { text: text.to_string(), rect: TextRect::default(), confidence: None, words: Vec::new() })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_extract_field_splits_label_and_value() {
+        let field = extract_field(CropRegion::SummaryFirst, &result(&["KOPFGELD EINGESAMMELT", "2"]), None);
+
+        assert_eq!(field.label, Some(Label::BountyCollected));
+        assert_eq!(field.value, "2");
+        assert_eq!(field.raw_text, "KOPFGELD EINGESAMMELT 2");
+        assert_eq!(field.number, Some(2));
+        assert!(!field.suspicious);
+    }
+
+    #[test]
+    fn test_extract_field_corrects_upper_case_confusions() {
+        let field = extract_field(CropRegion::SummaryThird, &result(&["Hunters Killed", "D"]), Some(GameLanguage::English));
+
+        assert_eq!(field.label, Some(Label::HuntersKilled));
+        assert_eq!(field.value, "D");
+        assert_eq!(field.number, Some(0));
+        assert!(field.corrected);
+    }
+
+    #[test]
+    fn test_extract_username_keeps_text() {
+        let field = extract_field(CropRegion::SummaryUsername, &result(&[" Hunter_01 "]), Some(GameLanguage::English));
+
+        assert_eq!(field.label, None);
+        assert_eq!(field.value, "Hunter_01");
+    }
+
+    #[test]
+    fn test_correct_number_confusions() {
+        assert_eq!(correct_number("2", Some(Label::BountyCollected)), NumericValue { number: Some(2), corrected: false, suspicious: false });
+        assert_eq!(correct_number("O", Some(Label::BountyCollected)), NumericValue { number: Some(0), corrected: true, suspicious: false });
+        assert_eq!(correct_number("l2", Some(Label::MonstersKilled)), NumericValue { number: Some(12), corrected: true, suspicious: false });
+        assert_eq!(correct_number("S", Some(Label::HuntersKilled)), NumericValue { number: Some(5), corrected: true, suspicious: false });
+    }
+
+    #[test]
+    fn test_correct_number_separators() {
+        assert_eq!(correct_number("1,250", None).number, Some(1250));
+        assert_eq!(correct_number("1.250", None).number, Some(1250));
+        assert_eq!(correct_number("1 250", None).number, Some(1250));
+        assert_eq!(correct_number("1'2B0", None), NumericValue { number: Some(1280), corrected: true, suspicious: false });
+        assert!(correct_number("1,25", None).suspicious);
+    }
+
+    #[test]
+    fn test_correct_number_flags_out_of_range_and_missing() {
+        assert!(correct_number("7", Some(Label::BountyCollected)).suspicious);
+        assert_eq!(correct_number("", Some(Label::RiftsClosed)), NumericValue { number: None, corrected: false, suspicious: true });
+    }
+
+    #[test]
+    fn test_correct_number_flags_words_without_digits() {
+        for word in ["is", "Oil", "so", "Bob"] {
+            assert!(correct_number(word, None).suspicious, "{} should be flagged", word);
+        }
+        assert_eq!(correct_number("is", Some(Label::HuntersKilled)), NumericValue { number: Some(15), corrected: true, suspicious: true });
+        assert_eq!(correct_number("Bob 3", Some(Label::HuntersKilled)).number, Some(3));
+    }
+}
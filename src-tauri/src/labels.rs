@@ -0,0 +1,237 @@
+use std::ops::Range;
+
+use tauri::{AppHandle, Runtime};
+
+use crate::db;
+
+/// Languages of the game client we have label dictionaries for.
+/// The bundled ocrs model only reads Latin script, a Russian client needs `game_language`
+/// set to `ru` and a Tesseract install with the `rus` language data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GameLanguage {
+    English,
+    German,
+    French,
+    Russian,
+    Portuguese,
+}
+
+/// Labels that identify the summary screen and its cards
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Label {
+    MissionSummary,
+    BountyCollected,
+    RiftsClosed,
+    HuntersKilled,
+    MonstersKilled,
+    Extracted,
+}
+
+impl Label {
+    /// Labels shown as headings on the summary cards
+    pub const CARDS: [Label; 5] = [
+        Label::BountyCollected,
+        Label::RiftsClosed,
+        Label::HuntersKilled,
+        Label::MonstersKilled,
+        Label::Extracted,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Label::MissionSummary => "mission_summary",
+            Label::BountyCollected => "bounty_collected",
+            Label::RiftsClosed => "rifts_closed",
+            Label::HuntersKilled => "hunters_killed",
+            Label::MonstersKilled => "monsters_killed",
+            Label::Extracted => "extracted",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Label> {
+        [Label::MissionSummary]
+            .into_iter()
+            .chain(Label::CARDS)
+            .find(|label| label.as_str() == value)
+    }
+}
+
+impl GameLanguage {
+    pub const ALL: [GameLanguage; 5] = [
+        GameLanguage::English,
+        GameLanguage::German,
+        GameLanguage::French,
+        GameLanguage::Russian,
+        GameLanguage::Portuguese,
+    ];
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            GameLanguage::English => "en",
+            GameLanguage::German => "de",
+            GameLanguage::French => "fr",
+            GameLanguage::Russian => "ru",
+            GameLanguage::Portuguese => "pt",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<GameLanguage> {
+        let code = code.trim().to_lowercase();
+        GameLanguage::ALL.into_iter().find(|language| language.code() == code)
+    }
+
+    /// Tesseract language data for clients whose labels aren't in Latin script.
+    /// Those are recognized with Tesseract only, Latin script clients read fine with its default data.
+    pub fn tesseract_language(&self) -> Option<&'static str> {
+        match self {
+            GameLanguage::Russian => Some("rus"),
+            _ => None,
+        }
+    }
+
+    /// Spellings of a label in this language, lowercase and without diacritics.
+    /// OCR regularly drops accents, so every entry is compared against folded text.
+    pub fn labels(&self, label: Label) -> &'static [&'static str] {
+        use GameLanguage::*;
+        use Label::*;
+
+        match (self, label) {
+            (English, MissionSummary) => &["mission summary"],
+            (English, BountyCollected) => &["bounty collected"],
+            (English, RiftsClosed) => &["rifts closed"],
+            (English, HuntersKilled) => &["hunters killed"],
+            (English, MonstersKilled) => &["monsters killed"],
+            (English, Extracted) => &["extracted"],
+
+            (German, MissionSummary) => &["missionsubersicht", "missionszusammenfassung", "einsatzubersicht"],
+            (German, BountyCollected) => &["kopfgeld eingesammelt", "kopfgeld gesammelt"],
+            (German, RiftsClosed) => &["risse geschlossen"],
+            (German, HuntersKilled) => &["jager getotet"],
+            (German, MonstersKilled) => &["monster getotet"],
+            (German, Extracted) => &["entkommen", "extrahiert"],
+
+            (French, MissionSummary) => &["resume de la mission", "resume de mission"],
+            (French, BountyCollected) => &["prime recuperee", "prime collectee"],
+            (French, RiftsClosed) => &["failles fermees"],
+            (French, HuntersKilled) => &["chasseurs tues"],
+            (French, MonstersKilled) => &["monstres tues"],
+            (French, Extracted) => &["extrait", "evacue"],
+
+            (Russian, MissionSummary) => &["итоги задания", "сводка задания", "итоги миссии"],
+            (Russian, BountyCollected) => &["награда получена", "трофеи собраны"],
+            (Russian, RiftsClosed) => &["разломы закрыты"],
+            (Russian, HuntersKilled) => &["убито охотников", "охотники убиты"],
+            (Russian, MonstersKilled) => &["убито монстров", "монстры убиты"],
+            (Russian, Extracted) => &["эвакуирован", "эвакуация"],
+
+            (Portuguese, MissionSummary) => &["resumo da missao"],
+            (Portuguese, BountyCollected) => &["recompensa coletada", "recompensa obtida"],
+            (Portuguese, RiftsClosed) => &["fendas fechadas"],
+            (Portuguese, HuntersKilled) => &["cacadores mortos"],
+            (Portuguese, MonstersKilled) => &["monstros mortos"],
+            (Portuguese, Extracted) => &["extraido"],
+        }
+    }
+
+    /// Returns the spelling of a label found in the text, if any
+    pub fn find_label(&self, label: Label, text: &str) -> Option<&'static str> {
+        let folded = normalize(text);
+        self.labels(label).iter().copied().find(|spelling| folded.contains(spelling))
+    }
+
+    pub fn contains(&self, label: Label, text: &str) -> bool {
+        self.find_label(label, text).is_some()
+    }
+}
+
+/// The language configured in the `game_language` setting, `None` means auto-detect
+pub async fn configured_language<R: Runtime>(app: &AppHandle<R>) -> Option<GameLanguage> {
+    db::setting(app, "game_language").await.and_then(|value| GameLanguage::from_code(&value))
+}
+
+/// Finds the language whose mission summary heading appears in the text
+pub fn detect_language(text: &str) -> Option<GameLanguage> {
+    GameLanguage::ALL
+        .into_iter()
+        .find(|language| language.contains(Label::MissionSummary, text))
+}
+
+/// Checks the `MissionSummary` crop text, honouring the configured language.
+/// Returns the language the heading was found in.
+pub async fn match_mission_summary<R: Runtime>(app: &AppHandle<R>, text: &str) -> Option<GameLanguage> {
+    match configured_language(app).await {
+        Some(language) => Some(language).filter(|language| language.contains(Label::MissionSummary, text)),
+        None => detect_language(text),
+    }
+}
+
+/// Finds which card heading appears in a summary card's text
+pub fn find_card_label(language: GameLanguage, text: &str) -> Option<Label> {
+    Label::CARDS.into_iter().find(|&label| language.contains(label, text))
+}
+
+/// Lowercases, folds diacritics and collapses whitespace
+pub fn normalize(text: &str) -> String {
+    normalize_spans(text).into_iter().map(|(c, _)| c).collect()
+}
+
+/// `normalize` keeping, for every output char, the byte range of the input char it came from,
+/// so a match in the normalized text can be cut out of the original
+pub fn normalize_spans(text: &str) -> Vec<(char, Range<usize>)> {
+    let mut spans: Vec<(char, Range<usize>)> = Vec::new();
+    let mut pending_space: Option<Range<usize>> = None;
+
+    for (start, c) in text.char_indices() {
+        let span = start..start + c.len_utf8();
+        if c.is_whitespace() {
+            pending_space.get_or_insert(span);
+            continue;
+        }
+        if let Some(space) = pending_space.take().filter(|_| !spans.is_empty()) {
+            spans.push((' ', space));
+        }
+
+        for lower in c.to_lowercase() {
+            let replacement: &[char] = match lower {
+                'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' => &['a'],
+                'ç' => &['c'],
+                'è' | 'é' | 'ê' | 'ë' => &['e'],
+                'ì' | 'í' | 'î' | 'ï' => &['i'],
+                'ñ' => &['n'],
+                'ò' | 'ó' | 'ô' | 'õ' | 'ö' => &['o'],
+                'ù' | 'ú' | 'û' | 'ü' => &['u'],
+                'ß' => &['s', 's'],
+                'ё' => &['е'],
+                _ => &[lower],
+            };
+            spans.extend(replacement.iter().map(|&folded| (folded, span.clone())));
+        }
+    }
+
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_language() {
+        assert_eq!(detect_language("MISSION SUMMARY"), Some(GameLanguage::English));
+        assert_eq!(detect_language("MISSIONSÜBERSICHT"), Some(GameLanguage::German));
+        assert_eq!(detect_language("Résumé de la  mission"), Some(GameLanguage::French));
+        assert_eq!(detect_language("ИТОГИ ЗАДАНИЯ"), Some(GameLanguage::Russian));
+        assert_eq!(detect_language("RESUMO DA MISSÃO"), Some(GameLanguage::Portuguese));
+        assert_eq!(detect_language("LOADOUT"), None);
+    }
+
+    #[test]
+    fn test_find_card_label() {
+        assert_eq!(find_card_label(GameLanguage::English, "BOUNTY COLLECTED 2"), Some(Label::BountyCollected));
+        assert_eq!(find_card_label(GameLanguage::German, "RISSE GESCHLOSSEN 3"), Some(Label::RiftsClosed));
+        assert_eq!(find_card_label(GameLanguage::French, "CHASSEURS TUÉS 4"), Some(Label::HuntersKilled));
+        assert_eq!(find_card_label(GameLanguage::English, "RISSE GESCHLOSSEN 3"), None);
+    }
+}
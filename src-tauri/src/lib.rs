@@ -1,25 +1,50 @@
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 use tauri::Manager;
 use anyhow::anyhow;
-use std::sync::Mutex;
+use tracing::{error, info};
 
 #[derive(Default)]
 pub struct AppState {
-    pub db: Option<Mutex<db::DbConnection>>,
+    pub db: Option<db::DbPool>,
 }
 
+pub mod ai_models;
+pub mod api;
+pub mod calibration;
 pub mod db;
 pub mod models;
 mod commands;
 pub mod screenshot;
 pub mod shortcuts;
 pub mod crop;
+pub mod debug;
+pub mod diagnostics;
+pub mod encoding;
+pub mod error;
+pub mod fields;
 pub mod ocr;
+pub mod overlay;
+pub mod pipeline;
+pub mod preprocess;
+pub mod privacy;
+pub mod labels;
+pub mod localize;
+pub mod logging;
 pub mod migrations;
+pub mod reprocess;
+pub mod retention;
+pub mod stats;
+pub mod thumbnail;
+pub mod viewport;
+pub mod webhook;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     let migrations = migrations::get_migrations();
+    let context = tauri::generate_context!();
+
+    // Cap OCR threads while this is still the only thread
+    pipeline::configure_threads(&context.config().identifier);
 
     tauri::Builder::default()
         .on_window_event(|window, event| match event {
@@ -30,7 +55,7 @@ pub fn run() {
                     for (label, win) in windows {
                         if label != "main" {
                             if let Err(e) = win.close() {
-                                eprintln!("Failed to close window {}: {}", label, e);
+                                error!("Failed to close window {}: {}", label, e);
                             }
                         }
                     }
@@ -46,27 +71,56 @@ pub fn run() {
         .setup(|app| {
             tauri::async_runtime::block_on(async move {
                 let app_handle = app.handle();
-                let conn = db::init(&app_handle);
-                
-                // Store the database connection in the app state
-                app.manage(AppState {
-                    db: Some(Mutex::new(conn)),
-                });
+
+                // Start logging first so problems during startup end up in the log files
+                match logging::init(&app_handle) {
+                    Ok(dir) => info!("Logging to {}", dir.display()),
+                    Err(e) => eprintln!("Failed to initialize logging: {}", e),
+                }
+
+                let pool = match db::init(&app_handle) {
+                    Ok(pool) => Some(pool),
+                    Err(e) => {
+                        error!("Failed to open database: {}", e);
+                        None
+                    }
+                };
+
+                // Store the connection pool in the app state
+                app.manage(AppState { db: pool });
 
                 // Initialize system info
-                if let Some(db_mutex) = app.state::<AppState>().db.as_ref() {
-                    if let Ok(mut conn) = db_mutex.lock() {
-                        if let Err(e) = db::save_system_info(&mut conn).await {
-                            eprintln!("Failed to save system info: {}", e);
-                        }
+                if let Ok(mut conn) = db::connection(&app_handle) {
+                    if let Err(e) = db::save_system_info(&mut conn).await {
+                        error!("Failed to save system info: {}", e);
                     }
                 }
 
+                // Check the OCR models so a missing file is reported before the first capture
+                ai_models::check_on_startup(&app.handle());
+
+                info!("OCR limited to {} worker threads", pipeline::worker_count());
+
                 // Set up the shortcut handler first
                 shortcuts::setup_shortcut_handler(&app.handle()).map_err(|e| anyhow!("Failed to setup shortcut handler: {}", e))?;
                 
                 // Then register the initial shortcuts
                 shortcuts::register_shortcuts(&app.handle()).map_err(|e| anyhow!("Failed to register shortcuts: {}", e))?;
+
+                // Keep the debug image folder within the configured retention limits
+                retention::start_maintenance(&app.handle());
+
+                // Render thumbnails for screenshots saved before they were generated
+                thumbnail::start_backfill(&app.handle());
+
+                // Serve the local API for external tools when it is enabled
+                api::start(&app.handle()).await;
+
+                // Serve the stream overlay, its session stats count from here
+                overlay::start(&app.handle()).await;
+
+                // Post queued match results, including those left over from the last session
+                webhook::start_delivery(&app.handle());
                 Ok(())
             })
         })
@@ -75,7 +129,7 @@ pub fn run() {
         .plugin(tauri_plugin_window_state::Builder::new().build())
         .plugin(tauri_plugin_single_instance::init(|app, argv, _cwd| {
             // handle second instance here
-            println!("new app instance with args: {argv:?}");
+            info!("new app instance with args: {argv:?}");
             // the deep link event was already triggered at this point
 
             // bring the main window to the foreground
@@ -87,8 +141,30 @@ pub fn run() {
         .plugin(tauri_plugin_opener::init())
         .invoke_handler(tauri::generate_handler![
             commands::submit_screenshot,
-            commands::reload_shortcut
+            commands::reload_shortcut,
+            commands::get_ocr_results,
+            commands::get_match_fields,
+            commands::update_match_fields,
+            commands::get_corrections,
+            commands::get_stats,
+            commands::preview_preprocessing,
+            commands::get_model_status,
+            commands::compare_ocr_backends,
+            commands::get_disk_usage,
+            commands::apply_retention,
+            commands::reprocess_matches,
+            commands::cancel_reprocess,
+            commands::get_logs,
+            commands::create_diagnostics_bundle,
+            commands::calibrate_regions,
+            commands::compare_capture_formats,
+            commands::get_thumbnail,
+            commands::reload_api,
+            commands::get_api_token,
+            commands::send_test_webhook,
+            commands::reload_overlay,
+            commands::redact_stored_screenshots
         ])
-        .run(tauri::generate_context!())
+        .run(context)
         .expect("error while running tauri application");
 }
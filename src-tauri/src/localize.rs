@@ -0,0 +1,262 @@
+use image::DynamicImage;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tauri::AppHandle;
+use tracing::{debug, info, warn};
+
+use crate::calibration;
+use crate::crop::{self, CropRegion, BASE_HEIGHT, BASE_WIDTH};
+use crate::db;
+use crate::labels::{self, GameLanguage, Label};
+use crate::ocr::{self, OcrResult, TextRect};
+
+/// `(x, y, width, height)` of a region in capture pixels
+pub type Rect = (u32, u32, u32, u32);
+
+/// Furthest an anchor may be from where the fixed layout expects it, in 1920x1080 pixels.
+/// Half the distance between two summary card rows, so a label can't be taken for its neighbour's.
+const MAX_SHIFT: f32 = 50.0;
+
+/// How regions are positioned, from the `region_localization` setting
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LocalizationMode {
+    /// Fixed rectangles from the crop configs
+    Fixed,
+    /// Rectangles positioned relative to headings found by text detection on the full frame
+    Anchors,
+}
+
+impl LocalizationMode {
+    pub fn parse(value: &str) -> Option<LocalizationMode> {
+        match value.trim().to_lowercase().as_str() {
+            "fixed" => Some(LocalizationMode::Fixed),
+            "anchors" => Some(LocalizationMode::Anchors),
+            _ => None,
+        }
+    }
+}
+
+pub async fn mode(app: &AppHandle) -> LocalizationMode {
+    db::setting(app, "region_localization")
+        .await
+        .and_then(|v| LocalizationMode::parse(&v))
+        .unwrap_or(LocalizationMode::Fixed)
+}
+
+/// Where each region sits in one capture, regions without an entry use the fixed rectangle
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct RegionLayout {
+    rects: HashMap<CropRegion, Rect>,
+}
+
+impl RegionLayout {
+    /// The fixed layout from the crop configs
+    pub fn fixed() -> Self {
+        RegionLayout::default()
+    }
+
+    pub fn from_rects(rects: HashMap<CropRegion, Rect>) -> Self {
+        RegionLayout { rects }
+    }
+
+    pub fn rect(&self, width: u32, height: u32, region: CropRegion) -> Rect {
+        self.rects
+            .get(&region)
+            .copied()
+            .unwrap_or_else(|| crop::region_rect(width, height, region))
+    }
+
+    pub fn is_fixed(&self) -> bool {
+        self.rects.is_empty()
+    }
+}
+
+/// Builds the layout for a capture with the configured localization mode.
+/// The calibrated regions for the capture's resolution replace the fixed ones when present.
+/// Text detection runs on a blocking thread, any failure falls back to that base layout.
+pub async fn locate(app: &AppHandle, image: Arc<DynamicImage>) -> RegionLayout {
+    let base = calibration::load(app, image.width(), image.height())
+        .await
+        .map(|calibration| calibration.layout())
+        .unwrap_or_else(RegionLayout::fixed);
+    if mode(app).await == LocalizationMode::Fixed {
+        return base;
+    }
+
+    let language = labels::configured_language(app).await;
+    let app = app.clone();
+    let fallback = base.clone();
+    match tokio::task::spawn_blocking(move || locate_anchors(&app, &image, base, language)).await {
+        Ok(layout) => layout,
+        Err(e) => {
+            warn!("Region localization task failed, using fixed regions: {}", e);
+            fallback
+        }
+    }
+}
+
+fn locate_anchors(app: &AppHandle, image: &DynamicImage, base: RegionLayout, language: Option<GameLanguage>) -> RegionLayout {
+    let result = match ocr::recognize_image(app, image) {
+        Ok(result) => result,
+        Err(e) => {
+            warn!("Text detection on the full frame failed, using fixed regions: {}", e);
+            return base;
+        }
+    };
+
+    let anchors = find_anchors(&base, image.width(), image.height(), &result, language);
+    if anchors.is_empty() {
+        info!("No anchor labels found, using fixed regions");
+        return base;
+    }
+
+    debug!("Anchors found for {:?}", anchors.keys().collect::<Vec<_>>());
+    layout_from_anchors(&base, image.width(), image.height(), &anchors)
+}
+
+/// Offset of a region's heading from the region's top-left corner in the 1920x1080 layout.
+/// Only regions with a heading of their own can be anchored.
+pub(crate) fn anchor_offset(region: CropRegion) -> Option<(f32, f32)> {
+    match region {
+        CropRegion::MissionSummary => Some((12.0, 28.0)),
+        CropRegion::SummaryFirst | CropRegion::SummarySecond | CropRegion::SummaryThird | CropRegion::SummaryFourth => Some((14.0, 10.0)),
+        CropRegion::SummaryUsername => None,
+    }
+}
+
+/// Where a layout expects the heading of a region
+fn expected_anchor(base: &RegionLayout, width: u32, height: u32, region: CropRegion) -> Option<(f32, f32)> {
+    let (offset_x, offset_y) = anchor_offset(region)?;
+    let (x, y, _, _) = base.rect(width, height, region);
+    let scale_x = width as f32 / BASE_WIDTH as f32;
+    let scale_y = height as f32 / BASE_HEIGHT as f32;
+    Some((x as f32 + offset_x * scale_x, y as f32 + offset_y * scale_y))
+}
+
+/// Finds the mission summary heading and the card headings among the detected lines.
+/// Card headings are assigned to the card whose expected heading position is nearest.
+fn find_anchors(base: &RegionLayout, width: u32, height: u32, result: &OcrResult, language: Option<GameLanguage>) -> HashMap<CropRegion, TextRect> {
+    let languages: Vec<GameLanguage> = match language {
+        Some(language) => vec![language],
+        None => GameLanguage::ALL.to_vec(),
+    };
+    let max_shift = MAX_SHIFT * width as f32 / BASE_WIDTH as f32;
+
+    let mut anchors: HashMap<CropRegion, (TextRect, f32)> = HashMap::new();
+    for line in &result.lines {
+        let is_heading = languages.iter().any(|language| language.contains(Label::MissionSummary, &line.text));
+        let is_card = languages.iter().any(|&language| labels::find_card_label(language, &line.text).is_some());
+        let candidates: &[CropRegion] = if is_heading {
+            &[CropRegion::MissionSummary]
+        } else if is_card {
+            &[CropRegion::SummaryFirst, CropRegion::SummarySecond, CropRegion::SummaryThird, CropRegion::SummaryFourth]
+        } else {
+            continue;
+        };
+
+        let nearest = candidates
+            .iter()
+            .filter_map(|&region| {
+                let (x, y) = expected_anchor(base, width, height, region)?;
+                let distance = ((line.rect.x as f32 - x).powi(2) + (line.rect.y as f32 - y).powi(2)).sqrt();
+                Some((region, distance))
+            })
+            .filter(|&(_, distance)| distance <= max_shift)
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+
+        if let Some((region, distance)) = nearest {
+            let closer = anchors.get(&region).map_or(true, |&(_, best)| distance < best);
+            if closer {
+                anchors.insert(region, (line.rect, distance));
+            }
+        }
+    }
+
+    anchors.into_iter().map(|(region, (rect, _))| (region, rect)).collect()
+}
+
+/// Moves every region of the base layout by how far its heading is from the expected position.
+/// Regions without a heading found move by the median shift of the headings that were.
+fn layout_from_anchors(base: &RegionLayout, width: u32, height: u32, anchors: &HashMap<CropRegion, TextRect>) -> RegionLayout {
+    let mut shifts: HashMap<CropRegion, (i64, i64)> = HashMap::new();
+    for (&region, rect) in anchors {
+        if let Some((x, y)) = expected_anchor(base, width, height, region) {
+            shifts.insert(region, ((rect.x as f32 - x).round() as i64, (rect.y as f32 - y).round() as i64));
+        }
+    }
+    if shifts.is_empty() {
+        return base.clone();
+    }
+
+    let median = |mut values: Vec<i64>| {
+        values.sort_unstable();
+        values[values.len() / 2]
+    };
+    let common_shift = (
+        median(shifts.values().map(|shift| shift.0).collect()),
+        median(shifts.values().map(|shift| shift.1).collect()),
+    );
+
+    let rects = CropRegion::ALL
+        .into_iter()
+        .map(|region| {
+            let (x, y, region_width, region_height) = base.rect(width, height, region);
+            let (shift_x, shift_y) = shifts.get(&region).copied().unwrap_or(common_shift);
+            let x = (x as i64 + shift_x).clamp(0, width.saturating_sub(region_width) as i64) as u32;
+            let y = (y as i64 + shift_y).clamp(0, height.saturating_sub(region_height) as i64) as u32;
+            (region, (x, y, region_width, region_height))
+        })
+        .collect();
+
+    RegionLayout { rects }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ocr::OcrLine;
+
+    fn line(text: &str, x: i32, y: i32) -> OcrLine {
+        OcrLine {
+            text: text.to_string(),
+            rect: TextRect { x, y, width: 200, height: 30 },
+            confidence: None,
+            words: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_regions_follow_anchors() {
+        let result = OcrResult {
+            lines: vec![
+                line("MISSION SUMMARY", 146, 127),
+                line("BOUNTY COLLECTED", 243, 214),
+                // Too far from any card to be trusted
+                line("HUNTERS KILLED", 1500, 800),
+                line("2", 250, 250),
+            ],
+        };
+
+        let fixed = RegionLayout::fixed();
+        let anchors = find_anchors(&fixed, 1920, 1080, &result, None);
+        assert_eq!(anchors.len(), 2);
+
+        let layout = layout_from_anchors(&fixed, 1920, 1080, &anchors);
+        assert_eq!(layout.rect(1920, 1080, CropRegion::MissionSummary), (134, 99, 300, 95));
+        assert_eq!(layout.rect(1920, 1080, CropRegion::SummaryFirst), (229, 204, 335, 90));
+        // No heading of its own, moved by the median shift
+        assert_eq!(layout.rect(1920, 1080, CropRegion::SummaryUsername), (170, 640, 400, 48));
+    }
+
+    #[test]
+    fn test_no_anchors_keeps_fixed_layout() {
+        let result = OcrResult { lines: vec![line("LOADOUT", 100, 100)] };
+        let fixed = RegionLayout::fixed();
+        let anchors = find_anchors(&fixed, 1920, 1080, &result, Some(GameLanguage::English));
+        let layout = layout_from_anchors(&fixed, 1920, 1080, &anchors);
+
+        assert!(layout.is_fixed());
+        assert_eq!(layout.rect(1920, 1080, CropRegion::SummaryFirst), crop::region_rect(1920, 1080, CropRegion::SummaryFirst));
+    }
+}
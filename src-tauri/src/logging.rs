@@ -0,0 +1,157 @@
+use once_cell::sync::OnceCell;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager, Runtime};
+use tracing::Level;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::{fmt, prelude::*, EnvFilter};
+
+const LOG_PREFIX: &str = "martini";
+const LOG_SUFFIX: &str = "log";
+/// Number of daily log files kept before the oldest is deleted
+const MAX_LOG_FILES: usize = 7;
+
+/// Keeps the background writer alive, dropping it would lose buffered entries
+static GUARD: OnceCell<WorkerGuard> = OnceCell::new();
+
+/// A single line of the JSON log files
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct LogEntry {
+    pub timestamp: String,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+    /// Structured fields other than the message
+    pub fields: serde_json::Map<String, serde_json::Value>,
+    /// Spans the entry was recorded in, outermost first, e.g. the capture job
+    pub spans: Vec<serde_json::Value>,
+}
+
+/// Sets up logging to daily rotating JSON files in the app log directory and to stdout.
+/// The level defaults to `info` and can be overridden with `RUST_LOG`.
+pub fn init<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, Box<dyn Error + Send + Sync>> {
+    let dir = log_dir(app)?;
+    std::fs::create_dir_all(&dir)?;
+
+    let appender = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(LOG_PREFIX)
+        .filename_suffix(LOG_SUFFIX)
+        .max_log_files(MAX_LOG_FILES)
+        .build(&dir)?;
+    let (writer, guard) = tracing_appender::non_blocking(appender);
+
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let file_layer = fmt::layer()
+        .json()
+        .with_current_span(false)
+        .with_span_list(true)
+        .with_writer(writer);
+    let stdout_layer = fmt::layer().with_target(false);
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(file_layer)
+        .with(stdout_layer)
+        .try_init()?;
+
+    let _ = GUARD.set(guard);
+    Ok(dir)
+}
+
+pub fn log_dir<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, Box<dyn Error + Send + Sync>> {
+    Ok(app.path().app_log_dir()?)
+}
+
+/// Returns the most recent entries at or above `min_level`, newest first
+pub fn read_logs(dir: &Path, min_level: Level, limit: usize) -> Result<Vec<LogEntry>, Box<dyn Error + Send + Sync>> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(LOG_PREFIX) && name.ends_with(LOG_SUFFIX))
+        })
+        .collect();
+    // File names end with the date, so the newest file sorts last
+    files.sort();
+
+    let mut entries = Vec::new();
+    for file in files.iter().rev() {
+        let content = std::fs::read_to_string(file)?;
+        for line in content.lines().rev() {
+            let Some(entry) = parse_line(line) else { continue };
+            if parse_level(&entry.level).is_some_and(|level| level <= min_level) {
+                entries.push(entry);
+                if entries.len() >= limit {
+                    return Ok(entries);
+                }
+            }
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Parses a level name, accepting any case
+pub fn parse_level(value: &str) -> Option<Level> {
+    value.trim().parse::<Level>().ok()
+}
+
+fn parse_line(line: &str) -> Option<LogEntry> {
+    let mut value: serde_json::Value = serde_json::from_str(line).ok()?;
+    let object = value.as_object_mut()?;
+
+    let mut fields = match object.remove("fields") {
+        Some(serde_json::Value::Object(fields)) => fields,
+        _ => serde_json::Map::new(),
+    };
+    let message = match fields.remove("message") {
+        Some(serde_json::Value::String(message)) => message,
+        Some(other) => other.to_string(),
+        None => String::new(),
+    };
+    let text = |key: &str| object.get(key).and_then(|v| v.as_str()).unwrap_or_default().to_string();
+
+    Some(LogEntry {
+        timestamp: text("timestamp"),
+        level: text("level"),
+        target: text("target"),
+        message,
+        fields,
+        spans: object.get("spans").and_then(|v| v.as_array()).cloned().unwrap_or_default(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_logs_filters_by_level() {
+        let dir = std::env::temp_dir().join(format!("martini_logs_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("martini.2026-01-01.log"),
+            concat!(
+                r#"{"timestamp":"2026-01-01T10:00:00Z","level":"INFO","fields":{"message":"Taking screenshot..."},"target":"martini_lib::shortcuts","spans":[{"job":1,"name":"capture"}]}"#, "\n",
+                r#"{"timestamp":"2026-01-01T10:00:01Z","level":"ERROR","fields":{"message":"Capture job failed","code":"window_not_found"},"target":"martini_lib::shortcuts"}"#, "\n",
+                "not json\n",
+                r#"{"timestamp":"2026-01-01T10:00:02Z","level":"DEBUG","fields":{"message":"OCR line"},"target":"martini_lib::ocr"}"#, "\n",
+            ),
+        )
+        .unwrap();
+
+        let warnings = read_logs(&dir, Level::WARN, 10).unwrap();
+        let all = read_logs(&dir, Level::TRACE, 2).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].message, "Capture job failed");
+        assert_eq!(warnings[0].fields["code"], "window_not_found");
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].level, "DEBUG");
+        assert_eq!(all[1].spans.len(), 0);
+    }
+}
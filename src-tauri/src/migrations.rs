@@ -49,5 +49,232 @@ pub fn get_migrations() -> Vec<Migration> {
                 "#,
             kind: MigrationKind::Up,
         },
+        Migration {
+            version: 4,
+            description: "Insert retention settings",
+            sql: r#"
+                    INSERT INTO settings (key, value) VALUES
+                        ('retention_max_age_days', '7'),
+                        ('retention_max_files', '200'),
+                        ('retention_max_size_mb', '500');
+                "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 5,
+            description: "Insert debug artifacts setting",
+            sql: r#"
+                    INSERT INTO settings (key, value) VALUES
+                        ('debug_artifacts', 'false');
+                "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 6,
+            description: "create ocr_results table",
+            sql: r#"
+                    CREATE TABLE IF NOT EXISTS ocr_results (
+                        id INTEGER PRIMARY KEY AUTOINCREMENT,
+                        screenshot_id INTEGER NOT NULL REFERENCES screenshots(id) ON DELETE CASCADE,
+                        region TEXT NOT NULL,
+                        result TEXT NOT NULL,
+                        created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+                        UNIQUE (screenshot_id, region)
+                    );
+                "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 7,
+            description: "Insert preprocessing settings",
+            sql: r#"
+                    INSERT INTO settings (key, value) VALUES
+                        ('preprocess_MissionSummary', '[{"op":"contrast","amount":30.0}]'),
+                        ('preprocess_SummaryFirst', '[{"op":"grayscale"},{"op":"upscale","factor":2.0},{"op":"invert"},{"op":"contrast","amount":30.0}]'),
+                        ('preprocess_SummarySecond', '[{"op":"grayscale"},{"op":"upscale","factor":2.0},{"op":"invert"},{"op":"contrast","amount":30.0}]'),
+                        ('preprocess_SummaryThird', '[{"op":"grayscale"},{"op":"upscale","factor":2.0},{"op":"invert"},{"op":"contrast","amount":30.0}]'),
+                        ('preprocess_SummaryFourth', '[{"op":"grayscale"},{"op":"upscale","factor":2.0},{"op":"invert"},{"op":"contrast","amount":30.0}]'),
+                        ('preprocess_SummaryUsername', '[{"op":"contrast","amount":30.0}]');
+                "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 8,
+            description: "Insert OCR thread limit setting",
+            sql: r#"
+                    INSERT INTO settings (key, value) VALUES
+                        ('ocr_max_threads', '0');
+                "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 9,
+            description: "Insert custom model directory setting",
+            sql: r#"
+                    INSERT INTO settings (key, value) VALUES
+                        ('model_dir', '');
+                "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 10,
+            description: "Insert OCR backend settings",
+            sql: r#"
+                    INSERT INTO settings (key, value) VALUES
+                        ('tesseract_path', 'tesseract'),
+                        ('ocr_backend_MissionSummary', 'ocrs'),
+                        ('ocr_backend_SummaryFirst', 'ocrs'),
+                        ('ocr_backend_SummarySecond', 'ocrs'),
+                        ('ocr_backend_SummaryThird', 'ocrs'),
+                        ('ocr_backend_SummaryFourth', 'ocrs'),
+                        ('ocr_backend_SummaryUsername', 'ocrs');
+                "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 11,
+            description: "create match_fields table and game language setting",
+            sql: r#"
+                    ALTER TABLE screenshots ADD COLUMN language TEXT;
+
+                    CREATE TABLE IF NOT EXISTS match_fields (
+                        id INTEGER PRIMARY KEY AUTOINCREMENT,
+                        screenshot_id INTEGER NOT NULL REFERENCES screenshots(id) ON DELETE CASCADE,
+                        region TEXT NOT NULL,
+                        label TEXT,
+                        raw_text TEXT NOT NULL,
+                        value TEXT NOT NULL,
+                        manual_label TEXT,
+                        created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+                        UNIQUE (screenshot_id, region)
+                    );
+
+                    INSERT INTO settings (key, value) VALUES
+                        ('game_language', 'auto');
+                "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 12,
+            description: "add numeric columns to match_fields",
+            sql: r#"
+                    ALTER TABLE match_fields ADD COLUMN number INTEGER;
+                    ALTER TABLE match_fields ADD COLUMN corrected BOOLEAN NOT NULL DEFAULT 0;
+                    ALTER TABLE match_fields ADD COLUMN suspicious BOOLEAN NOT NULL DEFAULT 0;
+                "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 13,
+            description: "create corrections table and manual field values",
+            sql: r#"
+                    ALTER TABLE match_fields ADD COLUMN manual_value TEXT;
+                    ALTER TABLE match_fields ADD COLUMN manual_number INTEGER;
+                    ALTER TABLE screenshots ADD COLUMN manual_mission_type TEXT;
+
+                    CREATE TABLE IF NOT EXISTS corrections (
+                        id INTEGER PRIMARY KEY AUTOINCREMENT,
+                        screenshot_id INTEGER NOT NULL REFERENCES screenshots(id) ON DELETE CASCADE,
+                        field TEXT NOT NULL,
+                        old_value TEXT,
+                        new_value TEXT NOT NULL,
+                        created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+                    );
+                "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 14,
+            description: "Insert capture strategy setting",
+            sql: r#"
+                    INSERT INTO settings (key, value) VALUES ('capture_strategy', 'auto');
+                "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 15,
+            description: "Insert region localization setting",
+            sql: r#"
+                    INSERT INTO settings (key, value) VALUES ('region_localization', 'fixed');
+                "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 16,
+            description: "Store the capture format with each screenshot",
+            sql: r#"
+                    ALTER TABLE screenshots ADD COLUMN image_format TEXT;
+                    INSERT INTO settings (key, value) VALUES ('capture_format', 'jpeg:100');
+                "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 17,
+            description: "Add screenshot thumbnails and region previews",
+            sql: r#"
+                    ALTER TABLE screenshots ADD COLUMN thumbnail TEXT;
+
+                    CREATE TABLE IF NOT EXISTS region_previews (
+                        id INTEGER PRIMARY KEY AUTOINCREMENT,
+                        screenshot_id INTEGER NOT NULL REFERENCES screenshots(id) ON DELETE CASCADE,
+                        region TEXT NOT NULL,
+                        image TEXT NOT NULL,
+                        created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+                        UNIQUE(screenshot_id, region)
+                    );
+                "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 18,
+            description: "Add local API settings",
+            sql: r#"
+                    INSERT INTO settings (key, value) VALUES ('api_enabled', 'false');
+                    INSERT INTO settings (key, value) VALUES ('api_port', '7373');
+                    INSERT INTO settings (key, value) VALUES ('api_token', '');
+                "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 19,
+            description: "Add webhook outbox and settings",
+            sql: r#"
+                    CREATE TABLE IF NOT EXISTS webhook_outbox (
+                        id INTEGER PRIMARY KEY AUTOINCREMENT,
+                        screenshot_id INTEGER REFERENCES screenshots(id) ON DELETE SET NULL,
+                        url TEXT NOT NULL,
+                        payload TEXT NOT NULL,
+                        status TEXT NOT NULL DEFAULT 'pending',
+                        attempts INTEGER NOT NULL DEFAULT 0,
+                        next_attempt_at TIMESTAMP NOT NULL,
+                        last_error TEXT,
+                        created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+                        delivered_at TIMESTAMP
+                    );
+
+                    INSERT INTO settings (key, value) VALUES ('webhook_urls', '');
+                    INSERT INTO settings (key, value) VALUES ('webhook_include_images', 'false');
+                "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 20,
+            description: "Add stream overlay settings",
+            sql: r#"
+                    INSERT INTO settings (key, value) VALUES ('overlay_enabled', 'false');
+                    INSERT INTO settings (key, value) VALUES ('overlay_port', '7374');
+                "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 21,
+            description: "Add privacy redaction settings",
+            sql: r#"
+                    INSERT INTO settings (key, value) VALUES ('privacy_mode', 'off');
+                    INSERT INTO settings (key, value) VALUES ('privacy_style', 'black');
+                    INSERT INTO settings (key, value) VALUES ('privacy_regions', 'SummaryUsername');
+                "#,
+            kind: MigrationKind::Up,
+        },
     ]
 }
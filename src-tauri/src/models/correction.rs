@@ -0,0 +1,26 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+#[derive(Debug, Clone, Queryable, Insertable, serde::Serialize)]
+#[diesel(table_name = corrections)]
+pub struct Correction {
+    #[diesel(deserialize_as = i32)]
+    pub id: Option<i32>,
+    pub screenshot_id: i32,
+    pub field: String,
+    pub old_value: Option<String>,
+    pub new_value: String,
+    pub created_at: NaiveDateTime,
+}
+
+// Define the schema
+diesel::table! {
+    corrections (id) {
+        id -> Integer,
+        screenshot_id -> Integer,
+        field -> Text,
+        old_value -> Nullable<Text>,
+        new_value -> Text,
+        created_at -> Timestamp,
+    }
+}
@@ -0,0 +1,74 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+#[derive(Queryable, Selectable, Insertable)]
+#[diesel(table_name = match_fields)]
+pub struct MatchField {
+    pub id: Option<i32>,
+    pub screenshot_id: i32,
+    pub region: String,
+    pub label: Option<String>,
+    pub raw_text: String,
+    pub value: String,
+    pub number: Option<i64>,
+    pub corrected: bool,
+    pub suspicious: bool,
+    pub created_at: NaiveDateTime,
+}
+
+/// A stored field as read back for the frontend
+#[derive(Debug, Clone, Queryable, Selectable, serde::Serialize)]
+#[diesel(table_name = match_fields)]
+pub struct MatchFieldRecord {
+    pub id: i32,
+    pub screenshot_id: i32,
+    pub region: String,
+    pub label: Option<String>,
+    pub raw_text: String,
+    pub value: String,
+    pub number: Option<i64>,
+    pub corrected: bool,
+    pub suspicious: bool,
+    pub manual_value: Option<String>,
+    pub manual_number: Option<i64>,
+    /// Card heading chosen by the user when OCR missed or misread it
+    pub manual_label: Option<String>,
+}
+
+impl MatchFieldRecord {
+    /// The value to use for display and stats, a manual correction wins over OCR
+    pub fn effective_value(&self) -> &str {
+        self.manual_value.as_deref().unwrap_or(&self.value)
+    }
+
+    pub fn effective_label(&self) -> Option<&str> {
+        self.manual_label.as_deref().or(self.label.as_deref())
+    }
+
+    pub fn effective_number(&self) -> Option<i64> {
+        if self.manual_value.is_some() {
+            self.manual_number
+        } else {
+            self.number
+        }
+    }
+}
+
+// Define the schema
+diesel::table! {
+    match_fields (id) {
+        id -> Integer,
+        screenshot_id -> Integer,
+        region -> Text,
+        label -> Nullable<Text>,
+        raw_text -> Text,
+        value -> Text,
+        number -> Nullable<BigInt>,
+        corrected -> Bool,
+        suspicious -> Bool,
+        manual_value -> Nullable<Text>,
+        manual_number -> Nullable<BigInt>,
+        manual_label -> Nullable<Text>,
+        created_at -> Timestamp,
+    }
+}
@@ -1,5 +1,15 @@
+pub mod correction;
+pub mod match_field;
+pub mod ocr_result;
+pub mod region_preview;
 pub mod screenshot;
 pub mod settings;
+pub mod webhook_delivery;
 
+pub use correction::*;
+pub use match_field::*;
+pub use ocr_result::*;
+pub use region_preview::*;
 pub use screenshot::*;
 pub use settings::*;
+pub use webhook_delivery::*;
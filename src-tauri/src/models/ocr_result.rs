@@ -0,0 +1,23 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+#[derive(Queryable, Selectable, Insertable)]
+#[diesel(table_name = ocr_results)]
+pub struct RegionOcr {
+    pub id: Option<i32>,
+    pub screenshot_id: i32,
+    pub region: String,
+    pub result: String,
+    pub created_at: NaiveDateTime,
+}
+
+// Define the schema
+diesel::table! {
+    ocr_results (id) {
+        id -> Integer,
+        screenshot_id -> Integer,
+        region -> Text,
+        result -> Text,
+        created_at -> Timestamp,
+    }
+}
@@ -0,0 +1,23 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+#[derive(Queryable, Selectable, Insertable)]
+#[diesel(table_name = region_previews)]
+pub struct RegionPreview {
+    pub id: Option<i32>,
+    pub screenshot_id: i32,
+    pub region: String,
+    pub image: String,
+    pub created_at: NaiveDateTime,
+}
+
+// Define the schema
+diesel::table! {
+    region_previews (id) {
+        id -> Integer,
+        screenshot_id -> Integer,
+        region -> Text,
+        image -> Text,
+        created_at -> Timestamp,
+    }
+}
@@ -16,6 +16,11 @@ pub struct Screenshot {
     pub summary_third: Option<String>,
     pub summary_fourth: Option<String>,
     pub summary_username: Option<String>,
+    pub language: Option<String>,
+    pub image_format: Option<String>,
+    pub thumbnail: Option<String>,
+    /// Mission type set by the user, `mission_type` keeps what was detected
+    pub manual_mission_type: Option<String>,
 }
 
 // Define the schema
@@ -33,5 +38,9 @@ diesel::table! {
         summary_third -> Nullable<Text>,
         summary_fourth -> Nullable<Text>,
         summary_username -> Nullable<Text>,
+        language -> Nullable<Text>,
+        image_format -> Nullable<Text>,
+        thumbnail -> Nullable<Text>,
+        manual_mission_type -> Nullable<Text>,
     }
 }
@@ -0,0 +1,36 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+/// A match result waiting to be posted to one webhook URL
+#[derive(Queryable, Selectable, Insertable)]
+#[diesel(table_name = webhook_outbox)]
+pub struct WebhookDelivery {
+    pub id: Option<i32>,
+    pub screenshot_id: Option<i32>,
+    pub url: String,
+    pub payload: String,
+    /// `pending` until delivered or given up on, then `delivered` or `failed`
+    pub status: String,
+    pub attempts: i32,
+    pub next_attempt_at: NaiveDateTime,
+    pub last_error: Option<String>,
+    pub created_at: NaiveDateTime,
+    /// Delivered rows are kept so a match is never posted twice
+    pub delivered_at: Option<NaiveDateTime>,
+}
+
+// Define the schema
+diesel::table! {
+    webhook_outbox (id) {
+        id -> Integer,
+        screenshot_id -> Nullable<Integer>,
+        url -> Text,
+        payload -> Text,
+        status -> Text,
+        attempts -> Integer,
+        next_attempt_at -> Timestamp,
+        last_error -> Nullable<Text>,
+        created_at -> Timestamp,
+        delivered_at -> Nullable<Timestamp>,
+    }
+}
@@ -1,16 +1,290 @@
 use std::error::Error;
 
-use ocrs::{ImageSource, OcrEngine, OcrEngineParams};
+use ocrs::{ImageSource, OcrEngine, OcrEngineParams, TextItem};
 use rten::Model;
 #[allow(unused)]
 use rten_tensor::prelude::*;
 
 use base64::{Engine, engine::general_purpose::STANDARD};
-use image::{self};
-use tauri::{AppHandle, Manager, path::BaseDirectory, Runtime, Emitter};
+use image::{self, DynamicImage};
+use std::io::Cursor;
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Runtime, Emitter};
+use tracing::{debug, warn};
+
+use crate::ai_models::{self, ModelKind, ModelStamp};
+use crate::crop::CropRegion;
+use crate::db;
+use crate::labels::GameLanguage;
+use crate::error::{AppError, AppResult, ErrorCode};
+
+/// Axis-aligned rectangle in pixels of the OCR input image
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct TextRect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl TextRect {
+    fn from_rect(rect: rten_imageproc::Rect) -> Self {
+        TextRect {
+            x: rect.left(),
+            y: rect.top(),
+            width: rect.width(),
+            height: rect.height(),
+        }
+    }
+}
+
+/// A recognized word with its position and, when the engine reports it, a confidence score
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct OcrWord {
+    pub text: String,
+    pub rect: TextRect,
+    /// Between 0 and 1, only set by the Tesseract backend. ocrs does not expose recognition
+    /// scores, so `None` means unknown rather than low confidence.
+    pub confidence: Option<f32>,
+}
+
+/// A recognized line of text made of words
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct OcrLine {
+    pub text: String,
+    pub rect: TextRect,
+    /// Mean of the word confidences, Tesseract only like `OcrWord::confidence`
+    pub confidence: Option<f32>,
+    pub words: Vec<OcrWord>,
+}
+
+/// Structured OCR output of a single image
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct OcrResult {
+    pub lines: Vec<OcrLine>,
+}
+
+impl OcrResult {
+    /// The text of every recognized line, in reading order
+    pub fn line_texts(&self) -> Vec<String> {
+        self.lines.iter().map(|line| line.text.clone()).collect()
+    }
+
+    /// All lines joined into a single string
+    pub fn text(&self) -> String {
+        self.line_texts().join(" ")
+    }
+
+    /// Iterates over every word of every line
+    pub fn words(&self) -> impl Iterator<Item = &OcrWord> {
+        self.lines.iter().flat_map(|line| line.words.iter())
+    }
+}
+
+/// A text recognition engine that turns a preprocessed crop into an `OcrResult`
+pub trait OcrBackend: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn recognize(&self, img: &DynamicImage) -> Result<OcrResult, Box<dyn Error + Send + Sync>>;
+}
+
+/// Backend choice for a region, read from the `ocr_backend_<Region>` setting.
+/// Accepts `ocrs`, `tesseract` or `tesseract:<whitelist>` such as `tesseract:0123456789`.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "backend", rename_all = "snake_case")]
+pub enum BackendConfig {
+    Ocrs,
+    Tesseract { whitelist: Option<String> },
+}
+
+impl BackendConfig {
+    pub fn parse(value: &str) -> Option<BackendConfig> {
+        let value = value.trim();
+        match value.split_once(':') {
+            Some((name, whitelist)) if name.eq_ignore_ascii_case("tesseract") => Some(BackendConfig::Tesseract {
+                whitelist: Some(whitelist.to_string()).filter(|w| !w.is_empty()),
+            }),
+            None if value.eq_ignore_ascii_case("tesseract") => Some(BackendConfig::Tesseract { whitelist: None }),
+            None if value.eq_ignore_ascii_case("ocrs") || value.is_empty() => Some(BackendConfig::Ocrs),
+            _ => None,
+        }
+    }
+
+    /// Loads the configured backend for a region, defaulting to ocrs
+    pub fn for_region(app: &AppHandle, region: CropRegion) -> BackendConfig {
+        let setting_key = format!("ocr_backend_{:?}", region);
+        match db::read_setting(app, &setting_key) {
+            Some(value) => BackendConfig::parse(&value).unwrap_or_else(|| {
+                warn!("Invalid {} setting '{}', using ocrs", setting_key, value);
+                BackendConfig::Ocrs
+            }),
+            None => BackendConfig::Ocrs,
+        }
+    }
+
+    /// Builds the backend, `language` is the Tesseract language data to recognize with
+    pub fn build(&self, app: &AppHandle, language: Option<&str>) -> Box<dyn OcrBackend> {
+        match self {
+            BackendConfig::Ocrs => Box::new(OcrsBackend { app: app.clone() }),
+            BackendConfig::Tesseract { whitelist } => Box::new(TesseractBackend {
+                binary: db::read_setting(app, "tesseract_path")
+                    .filter(|path| !path.trim().is_empty())
+                    .unwrap_or_else(|| "tesseract".to_string()),
+                whitelist: whitelist.clone(),
+                language: language.map(str::to_string),
+            }),
+        }
+    }
+}
+
+/// Returns the backend configured for a region. A game language that ocrs can't read,
+/// such as Russian, sends every region through Tesseract with that language's data.
+pub fn backend_for(app: &AppHandle, region: CropRegion) -> Box<dyn OcrBackend> {
+    let config = BackendConfig::for_region(app, region);
+    match (tesseract_language(app), config) {
+        (Some(language), BackendConfig::Ocrs) => BackendConfig::Tesseract { whitelist: None }.build(app, Some(language)),
+        (language, config) => config.build(app, language),
+    }
+}
+
+/// Tesseract language data needed for the configured game language, if any
+pub fn tesseract_language(app: &AppHandle) -> Option<&'static str> {
+    db::read_setting(app, "game_language")
+        .and_then(|value| GameLanguage::from_code(&value))
+        .and_then(|language| language.tesseract_language())
+}
+
+/// Recognition with the bundled ocrs models. Results never carry a confidence score.
+pub struct OcrsBackend {
+    app: AppHandle,
+}
+
+impl OcrBackend for OcrsBackend {
+    fn name(&self) -> &'static str {
+        "ocrs"
+    }
+
+    fn recognize(&self, img: &DynamicImage) -> Result<OcrResult, Box<dyn Error + Send + Sync>> {
+        recognize_image(&self.app, img)
+    }
+}
+
+/// Recognition with a locally installed Tesseract, driven through its command line
+pub struct TesseractBackend {
+    pub binary: String,
+    pub whitelist: Option<String>,
+    /// Passed as `-l`, Tesseract falls back to English without it
+    pub language: Option<String>,
+}
+
+impl OcrBackend for TesseractBackend {
+    fn name(&self) -> &'static str {
+        "tesseract"
+    }
+
+    fn recognize(&self, img: &DynamicImage) -> Result<OcrResult, Box<dyn Error + Send + Sync>> {
+        use std::io::Write;
+        use std::process::{Command, Stdio};
+
+        let mut png = Cursor::new(Vec::new());
+        img.write_to(&mut png, image::ImageFormat::Png)?;
+
+        // Page segmentation mode 6 treats the crop as a single block of text
+        let mut command = Command::new(&self.binary);
+        command.args(["stdin", "stdout", "--psm", "6"]);
+        if let Some(language) = &self.language {
+            command.args(["-l", language]);
+        }
+        if let Some(whitelist) = &self.whitelist {
+            command.args(["-c", &format!("tessedit_char_whitelist={}", whitelist)]);
+        }
+        command.arg("tsv");
+        command.stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped());
+
+        // Don't flash a console window for every crop on Windows
+        #[cfg(windows)]
+        {
+            use std::os::windows::process::CommandExt;
+            command.creation_flags(0x08000000);
+        }
+
+        let mut child = command
+            .spawn()
+            .map_err(|e| ocr_failed(format!("Failed to start Tesseract ({}): {}", self.binary, e)))?;
+        child.stdin.take().ok_or("Failed to open Tesseract stdin")?.write_all(&png.into_inner())?;
+
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            return Err(ocr_failed(format!("Tesseract failed: {}", String::from_utf8_lossy(&output.stderr).trim())));
+        }
+
+        Ok(parse_tesseract_tsv(&String::from_utf8_lossy(&output.stdout)))
+    }
+}
+
+fn ocr_failed(e: impl std::fmt::Display) -> Box<dyn Error + Send + Sync> {
+    AppError::new(ErrorCode::OcrFailed, e.to_string()).into()
+}
+
+/// Builds an `OcrResult` from Tesseract's TSV output, grouping words into lines
+fn parse_tesseract_tsv(tsv: &str) -> OcrResult {
+    let mut lines: Vec<((i32, i32, i32), OcrLine)> = Vec::new();
+
+    // Columns: level page block par line word left top width height conf text
+    for row in tsv.lines().skip(1) {
+        let columns: Vec<&str> = row.split('\t').collect();
+        if columns.len() < 12 || columns[0] != "5" {
+            continue;
+        }
+
+        let text = columns[11].trim();
+        if text.is_empty() {
+            continue;
+        }
+
+        let number = |index: usize| columns[index].trim().parse::<i32>().unwrap_or(0);
+        let line_key = (number(2), number(3), number(4));
+        let rect = TextRect { x: number(6), y: number(7), width: number(8), height: number(9) };
+        let confidence = columns[10].trim().parse::<f32>().ok().filter(|c| *c >= 0.0).map(|c| c / 100.0);
+        let word = OcrWord { text: text.to_string(), rect, confidence };
+
+        match lines.iter_mut().find(|(key, _)| *key == line_key) {
+            Some((_, line)) => line.words.push(word),
+            None => lines.push((line_key, OcrLine {
+                text: String::new(),
+                rect,
+                confidence: None,
+                words: vec![word],
+            })),
+        }
+    }
+
+    let lines = lines
+        .into_iter()
+        .map(|(_, mut line)| {
+            line.text = line.words.iter().map(|w| w.text.as_str()).collect::<Vec<_>>().join(" ");
+            line.rect = line.words.iter().skip(1).fold(line.words[0].rect, |acc, w| union_rect(acc, w.rect));
+
+            let scores: Vec<f32> = line.words.iter().filter_map(|w| w.confidence).collect();
+            if !scores.is_empty() {
+                line.confidence = Some(scores.iter().sum::<f32>() / scores.len() as f32);
+            }
+            line
+        })
+        .collect();
+
+    OcrResult { lines }
+}
+
+fn union_rect(a: TextRect, b: TextRect) -> TextRect {
+    let left = a.x.min(b.x);
+    let top = a.y.min(b.y);
+    let right = (a.x + a.width).max(b.x + b.width);
+    let bottom = (a.y + a.height).max(b.y + b.height);
+    TextRect { x: left, y: top, width: right - left, height: bottom - top }
+}
 
 #[tauri::command(async)]
-pub async fn perform_ocr<R: Runtime>(app: AppHandle<R>, base64_image: String) -> Result<Vec<String>, String> {
+pub async fn perform_ocr<R: Runtime>(app: AppHandle<R>, base64_image: String) -> AppResult<OcrResult> {
     let app_handle = app.clone();
     
     // Spawn a new thread for OCR processing
@@ -18,72 +292,154 @@ pub async fn perform_ocr<R: Runtime>(app: AppHandle<R>, base64_image: String) ->
         let result = process_ocr(&app_handle, &base64_image);
         
         match result {
-            Ok(texts) => {
+            Ok(ocr_result) => {
                 // Emit an event when OCR is complete
-                if let Err(e) = app_handle.emit("ocr-complete", texts.clone()) {
-                    println!("Failed to emit OCR complete event: {}", e);
+                if let Err(e) = app_handle.emit("ocr-complete", ocr_result.line_texts()) {
+                    warn!("Failed to emit OCR complete event: {}", e);
                 }
-                Ok(texts)
+                Ok(ocr_result)
             }
             Err(e) => {
                 // Emit an error event
-                if let Err(emit_err) = app_handle.emit("ocr-error", e.to_string()) {
-                    println!("Failed to emit OCR error event: {}", emit_err);
+                let error = AppError::from(e);
+                if let Err(emit_err) = app_handle.emit("ocr-error", &error) {
+                    warn!("Failed to emit OCR error event: {}", emit_err);
                 }
-                Err(e.to_string())
+                Err(error)
             }
         }
-    }).await.unwrap_or_else(|e| Err(e.to_string()))
+    }).await?
+}
+
+/// OCR engine shared by every job with the model files it was loaded from
+struct LoadedEngine {
+    stamps: Vec<Option<ModelStamp>>,
+    engine: Arc<OcrEngine>,
 }
 
-fn process_ocr<R: Runtime>(app: &AppHandle<R>, base64_image: &str) -> Result<Vec<String>, Box<dyn Error>> {
-    let detection_model_path = app.path().resolve("resources/ai_models/text-detection.rten", BaseDirectory::Resource)?;
-    let rec_model_path = app.path().resolve("resources/ai_models/text-recognition.rten", BaseDirectory::Resource)?;
-    println!("Detection model path: {}", detection_model_path.display());
-    println!("Recognition model path: {}", rec_model_path.display());
+static ENGINE: Mutex<Option<LoadedEngine>> = Mutex::new(None);
+
+/// Loads the models on first use and again whenever `model_dir` points elsewhere or a model
+/// file is replaced, jobs that already hold the previous engine finish with it
+fn engine<R: Runtime>(app: &AppHandle<R>) -> Result<Arc<OcrEngine>, Box<dyn Error + Send + Sync>> {
+    let stamps = ModelKind::ALL
+        .iter()
+        .map(|&kind| ai_models::model_stamp(app, kind))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut loaded = ENGINE.lock().unwrap();
+    if let Some(current) = loaded.as_ref().filter(|current| current.stamps == stamps) {
+        return Ok(current.engine.clone());
+    }
+
+    let detection_model_path = ai_models::resolve_model(app, ModelKind::Detection)?;
+    let rec_model_path = ai_models::resolve_model(app, ModelKind::Recognition)?;
+    debug!("Detection model path: {}", detection_model_path.display());
+    debug!("Recognition model path: {}", rec_model_path.display());
 
     // Emit progress event
     let _ = app.emit("ocr-progress", "Loading models...");
-    
+
     let detection_model = Model::load_file(detection_model_path)?;
     let recognition_model = Model::load_file(rec_model_path)?;
 
-    let engine = OcrEngine::new(OcrEngineParams {
+    let engine = Arc::new(OcrEngine::new(OcrEngineParams {
         detection_model: Some(detection_model),
         recognition_model: Some(recognition_model),
         ..Default::default()
-    })?;
+    })?);
 
-    // Emit progress event
-    let _ = app.emit("ocr-progress", "Processing image...");
+    *loaded = Some(LoadedEngine { stamps, engine: engine.clone() });
+    Ok(engine)
+}
 
+fn process_ocr<R: Runtime>(app: &AppHandle<R>, base64_image: &str) -> Result<OcrResult, Box<dyn Error + Send + Sync>> {
     // Decode base64 image
     let image_data = STANDARD.decode(base64_image)?;
-    let img = image::load_from_memory(&image_data)?.into_rgb8();
+    let img = image::load_from_memory(&image_data)?;
 
-    let img_source = ImageSource::from_bytes(img.as_raw(), img.dimensions())?;
-    let ocr_input = engine.prepare_input(img_source)?;
+    recognize_image(app, &img)
+}
+
+/// Runs text detection and recognition on an already decoded image
+pub fn recognize_image<R: Runtime>(app: &AppHandle<R>, img: &DynamicImage) -> Result<OcrResult, Box<dyn Error + Send + Sync>> {
+    let engine = engine(app)?;
+
+    // Emit progress event
+    let _ = app.emit("ocr-progress", "Processing image...");
+
+    let img = img.to_rgb8();
+    let img_source = ImageSource::from_bytes(img.as_raw(), img.dimensions()).map_err(ocr_failed)?;
+    let ocr_input = engine.prepare_input(img_source).map_err(ocr_failed)?;
 
     // Emit progress event
     let _ = app.emit("ocr-progress", "Detecting text...");
 
-    let word_rects = engine.detect_words(&ocr_input)?;
+    let word_rects = engine.detect_words(&ocr_input).map_err(ocr_failed)?;
     let line_rects = engine.find_text_lines(&ocr_input, &word_rects);
     
     // Emit progress event
     let _ = app.emit("ocr-progress", "Recognizing text...");
     
-    let line_texts = engine.recognize_text(&ocr_input, &line_rects)?;
+    let line_texts = engine.recognize_text(&ocr_input, &line_rects).map_err(ocr_failed)?;
 
-    let mut results = Vec::new();
+    // ocrs does not report recognition scores, so confidence is left empty
+    let mut lines = Vec::new();
     for line in line_texts
         .iter()
         .flatten()
         .filter(|l| l.to_string().len() > 1)
     {
-        println!("{}", line);
-        results.push(line.to_string());
+        debug!("{}", line);
+
+        let words = line
+            .words()
+            .map(|word| OcrWord {
+                text: word.to_string(),
+                rect: TextRect::from_rect(word.bounding_rect()),
+                confidence: None,
+            })
+            .collect();
+
+        lines.push(OcrLine {
+            text: line.to_string(),
+            rect: TextRect::from_rect(line.bounding_rect()),
+            confidence: None,
+            words,
+        });
     }
 
-    Ok(results)
+    Ok(OcrResult { lines })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_backend_config() {
+        assert_eq!(BackendConfig::parse("ocrs"), Some(BackendConfig::Ocrs));
+        assert_eq!(BackendConfig::parse("Tesseract"), Some(BackendConfig::Tesseract { whitelist: None }));
+        assert_eq!(
+            BackendConfig::parse("tesseract:0123456789"),
+            Some(BackendConfig::Tesseract { whitelist: Some("0123456789".to_string()) })
+        );
+        assert_eq!(BackendConfig::parse("paddle"), None);
+    }
+
+    #[test]
+    fn test_parse_tesseract_tsv() {
+        let tsv = "level\tpage_num\tblock_num\tpar_num\tline_num\tword_num\tleft\ttop\twidth\theight\tconf\ttext\n\
+            1\t1\t0\t0\t0\t0\t0\t0\t200\t50\t-1\t\n\
+            5\t1\t1\t1\t1\t1\t10\t5\t60\t20\t90\tBOUNTY\n\
+            5\t1\t1\t1\t1\t2\t80\t6\t90\t20\t80\tCOLLECTED\n\
+            5\t1\t1\t1\t2\t1\t10\t30\t12\t18\t95\t2\n";
+
+        let result = parse_tesseract_tsv(tsv);
+
+        assert_eq!(result.line_texts(), vec!["BOUNTY COLLECTED", "2"]);
+        let line = &result.lines[0];
+        assert_eq!(line.rect, TextRect { x: 10, y: 5, width: 160, height: 21 });
+        assert!((line.confidence.unwrap() - 0.85).abs() < 1e-6);
+    }
 }
@@ -0,0 +1,370 @@
+use axum::extract::{Query, State};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::Html;
+use axum::routing::get;
+use axum::{Json, Router};
+use chrono::{Local, NaiveDateTime, NaiveTime};
+use lazy_static::lazy_static;
+use once_cell::sync::OnceCell;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::Infallible;
+use std::error::Error;
+use std::sync::Mutex;
+use tauri::AppHandle;
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::{error, info, warn};
+
+use crate::api;
+use crate::db;
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::stats::{self, MatchStats};
+
+/// Port used when `overlay_port` is unset or invalid
+const DEFAULT_PORT: u16 = 7374;
+
+/// Events after which the overlay stats are recomputed
+const REFRESH_EVENTS: [&str; 2] = ["match-recognized", "reprocess-complete"];
+
+const DEFAULT_FONT_SIZE: u32 = 28;
+
+const TEMPLATE: &str = include_str!("overlay.html");
+
+lazy_static! {
+    /// Stops the running server, `None` when the overlay is disabled
+    static ref SHUTDOWN: Mutex<Option<oneshot::Sender<()>>> = Mutex::new(None);
+}
+
+/// When the app started, the default start of the stats shown on the overlay
+static SESSION_START: OnceCell<NaiveDateTime> = OnceCell::new();
+
+/// A value the overlay can show, picked with the `fields` query parameter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverlayField {
+    Matches,
+    Bounties,
+    Kills,
+    Monsters,
+    Extractions,
+    Rifts,
+}
+
+impl OverlayField {
+    /// Shown when no `fields` parameter is given
+    pub const DEFAULT: [OverlayField; 4] = [OverlayField::Matches, OverlayField::Bounties, OverlayField::Kills, OverlayField::Extractions];
+
+    pub fn parse(value: &str) -> Option<OverlayField> {
+        match value.trim().to_lowercase().as_str() {
+            "matches" => Some(OverlayField::Matches),
+            "bounties" | "bounty_collected" => Some(OverlayField::Bounties),
+            "kills" | "hunters_killed" => Some(OverlayField::Kills),
+            "monsters" | "monsters_killed" => Some(OverlayField::Monsters),
+            "extractions" | "extracted" => Some(OverlayField::Extractions),
+            "rifts" | "rifts_closed" => Some(OverlayField::Rifts),
+            _ => None,
+        }
+    }
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            OverlayField::Matches => "matches",
+            OverlayField::Bounties => "bounties",
+            OverlayField::Kills => "kills",
+            OverlayField::Monsters => "monsters",
+            OverlayField::Extractions => "extractions",
+            OverlayField::Rifts => "rifts",
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            OverlayField::Matches => "Matches",
+            OverlayField::Bounties => "Bounties",
+            OverlayField::Kills => "Kills",
+            OverlayField::Monsters => "Monsters",
+            OverlayField::Extractions => "Extractions",
+            OverlayField::Rifts => "Rifts",
+        }
+    }
+
+    pub fn value(&self, stats: &MatchStats) -> i64 {
+        let total = |label: &str| stats.totals.get(label).copied().unwrap_or(0);
+        match self {
+            OverlayField::Matches => stats.matches,
+            OverlayField::Bounties => total("bounty_collected"),
+            OverlayField::Kills => total("hunters_killed"),
+            OverlayField::Monsters => total("monsters_killed"),
+            OverlayField::Extractions => total("extracted"),
+            OverlayField::Rifts => total("rifts_closed"),
+        }
+    }
+}
+
+/// Which matches the overlay counts, from the `since` query parameter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverlaySince {
+    /// Since the app was started
+    Session,
+    Today,
+    All,
+    From(NaiveDateTime),
+}
+
+impl OverlaySince {
+    fn start(&self) -> Option<NaiveDateTime> {
+        match self {
+            OverlaySince::Session => Some(*SESSION_START.get_or_init(|| Local::now().naive_local())),
+            OverlaySince::Today => Some(Local::now().date_naive().and_time(NaiveTime::MIN)),
+            OverlaySince::All => None,
+            OverlaySince::From(since) => Some(*since),
+        }
+    }
+}
+
+/// Overlay look and content, every option comes from a query parameter
+#[derive(Debug, Clone)]
+pub struct OverlayOptions {
+    pub fields: Vec<OverlayField>,
+    /// Fields side by side instead of stacked
+    pub row: bool,
+    pub since: OverlaySince,
+    pub font_size: u32,
+    pub color: String,
+    pub background: String,
+    pub show_titles: bool,
+    pub title: Option<String>,
+}
+
+impl OverlayOptions {
+    /// Parses `fields`, `layout`, `since`, `font_size`, `color`, `background`, `titles` and `title`
+    pub fn from_query(params: &HashMap<String, String>) -> AppResult<Self> {
+        let param = |name: &str| params.get(name).map(|value| value.trim()).filter(|value| !value.is_empty());
+        let invalid = |name: &str, value: &str| AppError::new(ErrorCode::InvalidInput, format!("Invalid {}: {}", name, value));
+
+        let fields = match param("fields") {
+            Some(value) => value
+                .split(',')
+                .map(|name| OverlayField::parse(name).ok_or_else(|| invalid("field", name)))
+                .collect::<AppResult<Vec<_>>>()?,
+            None => OverlayField::DEFAULT.to_vec(),
+        };
+        let row = match param("layout") {
+            None | Some("row") => true,
+            Some("column") => false,
+            Some(other) => return Err(invalid("layout", other)),
+        };
+        let since = match param("since") {
+            None | Some("session") => OverlaySince::Session,
+            Some("today") => OverlaySince::Today,
+            Some("all") => OverlaySince::All,
+            Some(value) => OverlaySince::From(stats::parse_since(Some(value))?.ok_or_else(|| invalid("since", value))?),
+        };
+        let font_size = match param("font_size") {
+            Some(value) => value.parse::<u32>().ok().filter(|size| (8..=200).contains(size)).ok_or_else(|| invalid("font_size", value))?,
+            None => DEFAULT_FONT_SIZE,
+        };
+        let css = |name: &str, default: &str| match param(name) {
+            Some(value) if is_safe_css(value) => Ok(value.to_string()),
+            Some(value) => Err(invalid(name, value)),
+            None => Ok(default.to_string()),
+        };
+
+        Ok(OverlayOptions {
+            fields,
+            row,
+            since,
+            font_size,
+            color: css("color", "#ffffff")?,
+            background: css("background", "transparent")?,
+            show_titles: param("titles") != Some("false"),
+            title: param("title").map(str::to_string),
+        })
+    }
+
+    /// The selected fields and their values
+    pub fn values(&self, stats: &MatchStats) -> BTreeMap<&'static str, i64> {
+        self.fields.iter().map(|field| (field.key(), field.value(stats))).collect()
+    }
+}
+
+/// Colors are written into the page's CSS, only plain color values are let through
+fn is_safe_css(value: &str) -> bool {
+    value.len() <= 64 && value.chars().all(|c| c.is_ascii_alphanumeric() || " #(),.%".contains(c))
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// Starts the overlay server when `overlay_enabled` is set. It only listens on 127.0.0.1.
+/// The session shown by default starts here, even when the overlay is enabled later.
+pub async fn start(app: &AppHandle) {
+    SESSION_START.get_or_init(|| Local::now().naive_local());
+    if db::setting(app, "overlay_enabled").await.as_deref() != Some("true") {
+        return;
+    }
+
+    let port = db::setting(app, "overlay_port")
+        .await
+        .and_then(|v| v.trim().parse::<u16>().ok())
+        .unwrap_or(DEFAULT_PORT);
+
+    let (shutdown, stopped) = oneshot::channel();
+    if let Some(previous) = SHUTDOWN.lock().unwrap().replace(shutdown) {
+        let _ = previous.send(());
+    }
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = serve(app, port, stopped).await {
+            error!("Overlay server on port {} failed: {}", port, e);
+        }
+    });
+}
+
+/// Stops the server if it is running
+pub fn stop() {
+    if let Some(shutdown) = SHUTDOWN.lock().unwrap().take() {
+        let _ = shutdown.send(());
+    }
+}
+
+/// Applies changed overlay settings by restarting the server
+pub async fn reload(app: &AppHandle) {
+    stop();
+    start(app).await;
+}
+
+async fn serve(app: AppHandle, port: u16, stopped: oneshot::Receiver<()>) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let router = Router::new()
+        .route("/overlay", get(page))
+        .route("/overlay/stats", get(current_stats))
+        .route("/overlay/events", get(stream_stats))
+        .with_state(app);
+
+    let listener = tokio::net::TcpListener::bind(("127.0.0.1", port)).await?;
+    info!("Overlay available at http://127.0.0.1:{}/overlay", port);
+    axum::serve(listener, router)
+        .with_graceful_shutdown(async {
+            let _ = stopped.await;
+        })
+        .await?;
+    info!("Overlay on port {} stopped", port);
+    Ok(())
+}
+
+async fn page(Query(params): Query<HashMap<String, String>>) -> AppResult<Html<String>> {
+    let options = OverlayOptions::from_query(&params)?;
+    Ok(Html(render(&options)))
+}
+
+/// Fills the page template, values arrive through `/overlay/events` once the page loads
+fn render(options: &OverlayOptions) -> String {
+    let items: String = options
+        .fields
+        .iter()
+        .map(|field| {
+            let title = if options.show_titles { format!(r#"<span class="title">{}</span>"#, field.title()) } else { String::new() };
+            format!(r#"<div class="stat" data-field="{}">{}<span class="value">-</span></div>"#, field.key(), title)
+        })
+        .collect();
+    let heading = options
+        .title
+        .as_deref()
+        .map(|title| format!(r#"<div class="heading">{}</div>"#, escape_html(title)))
+        .unwrap_or_default();
+
+    TEMPLATE
+        .replace("{{direction}}", if options.row { "row" } else { "column" })
+        .replace("{{font_size}}", &options.font_size.to_string())
+        .replace("{{color}}", &options.color)
+        .replace("{{background}}", &options.background)
+        .replace("{{heading}}", &heading)
+        .replace("{{items}}", &items)
+}
+
+async fn current_stats(State(app): State<AppHandle>, Query(params): Query<HashMap<String, String>>) -> AppResult<Json<BTreeMap<&'static str, i64>>> {
+    let options = OverlayOptions::from_query(&params)?;
+    Ok(Json(values(&app, &options).await?))
+}
+
+async fn values(app: &AppHandle, options: &OverlayOptions) -> AppResult<BTreeMap<&'static str, i64>> {
+    let since = options.since.start();
+    let stats = db::run(app, move |conn| stats::collect(conn, since))
+        .await
+        .map_err(|e| e.context("Failed to compute overlay stats"))?;
+    Ok(options.values(&stats))
+}
+
+/// Server-sent events with the current values, sent again after every processed screenshot
+async fn stream_stats(State(app): State<AppHandle>, Query(params): Query<HashMap<String, String>>) -> AppResult<Sse<ReceiverStream<Result<Event, Infallible>>>> {
+    let options = OverlayOptions::from_query(&params)?;
+    let (sender, receiver) = mpsc::channel(4);
+    let mut events = api::events(&app).subscribe();
+
+    tokio::spawn(async move {
+        loop {
+            match values(&app, &options).await {
+                Ok(values) => {
+                    let data = serde_json::to_string(&values).unwrap_or_default();
+                    if sender.send(Ok(Event::default().data(data))).await.is_err() {
+                        return;
+                    }
+                }
+                Err(e) => warn!("Failed to refresh overlay stats: {}", e),
+            }
+
+            // Wait for the next processed screenshot, or stop once the page is gone
+            loop {
+                tokio::select! {
+                    event = events.recv() => match event {
+                        Ok(event) if REFRESH_EVENTS.contains(&event.event.as_str()) => break,
+                        Ok(_) => {}
+                        Err(broadcast::error::RecvError::Lagged(_)) => break,
+                        Err(broadcast::error::RecvError::Closed) => return,
+                    },
+                    _ = sender.closed() => return,
+                }
+            }
+        }
+    });
+
+    Ok(Sse::new(ReceiverStream::new(receiver)).keep_alive(KeepAlive::default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect()
+    }
+
+    #[test]
+    fn test_overlay_options_from_query() {
+        let options = OverlayOptions::from_query(&query(&[])).unwrap();
+        assert_eq!(options.fields, OverlayField::DEFAULT.to_vec());
+        assert_eq!(options.since, OverlaySince::Session);
+        assert!(options.row);
+
+        let options = OverlayOptions::from_query(&query(&[("fields", "kills, matches"), ("layout", "column"), ("since", "all"), ("color", "rgb(255, 200, 0)")])).unwrap();
+        assert_eq!(options.fields, vec![OverlayField::Kills, OverlayField::Matches]);
+        assert!(!options.row);
+        assert_eq!(options.color, "rgb(255, 200, 0)");
+
+        let stats = MatchStats {
+            matches: 3,
+            totals: HashMap::from([("hunters_killed".to_string(), 7)]),
+            ..Default::default()
+        };
+        assert_eq!(options.values(&stats), BTreeMap::from([("kills", 7), ("matches", 3)]));
+
+        assert!(OverlayOptions::from_query(&query(&[("fields", "deaths")])).is_err());
+        assert!(OverlayOptions::from_query(&query(&[("color", "red;}body{display:none")])).is_err());
+    }
+}
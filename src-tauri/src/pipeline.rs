@@ -0,0 +1,192 @@
+use diesel::Connection;
+use image::DynamicImage;
+use once_cell::sync::OnceCell;
+use std::error::Error;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+use tokio::sync::Semaphore;
+use tracing::warn;
+
+use crate::crop::{self, CropRegion};
+use crate::db;
+use crate::encoding::CaptureFormat;
+use crate::localize::{self, RegionLayout};
+use crate::ocr::{self, BackendConfig, OcrBackend, OcrResult};
+use crate::preprocess;
+
+/// Crop and OCR output of a single region
+pub struct RegionOutcome {
+    pub region: CropRegion,
+    /// Where the region was cropped from the capture
+    pub rect: localize::Rect,
+    pub crop: DynamicImage,
+    pub result: OcrResult,
+    pub crop_time: Duration,
+    pub ocr_time: Duration,
+}
+
+/// Worker count read from the settings when the app started
+static WORKERS: OnceCell<usize> = OnceCell::new();
+
+/// Number of regions processed at the same time, from the `ocr_max_threads` setting.
+/// A value of 0 uses half of the available cores so the game keeps most of the CPU.
+pub fn worker_count() -> usize {
+    *WORKERS.get_or_init(default_workers)
+}
+
+fn default_workers() -> usize {
+    let cores = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    (cores / 2).max(1)
+}
+
+/// Applies the CPU cap to the region workers and the inference thread pool.
+/// Must run before Tauri starts its runtime: `set_var` is unsound while other threads run,
+/// and the pool only reads `RTEN_NUM_THREADS` once, so changes to `ocr_max_threads` apply
+/// after a restart. `identifier` is the app identifier that names the AppData directory.
+pub fn configure_threads(identifier: &str) {
+    let workers = read_max_threads(identifier)
+        .filter(|&configured| configured > 0)
+        .unwrap_or_else(default_workers);
+    let _ = WORKERS.set(workers);
+
+    if std::env::var("RTEN_NUM_THREADS").is_err() {
+        std::env::set_var("RTEN_NUM_THREADS", workers.to_string());
+    }
+}
+
+/// Reads `ocr_max_threads` straight from the database file, the pool does not exist yet
+fn read_max_threads(identifier: &str) -> Option<usize> {
+    let path = dirs::data_dir()?.join(identifier).join("app.db");
+    // On the first start there is no database yet, opening it here would create an empty one
+    if !path.exists() {
+        return None;
+    }
+
+    let mut conn = db::DbConnection::establish(path.to_str()?).ok()?;
+    db::get_setting(&mut conn, "ocr_max_threads")?.trim().parse().ok()
+}
+
+/// Crops and recognizes every region of a capture on a bounded pool of blocking workers.
+/// The image is decoded and localized once and shared, results are returned in the order of `regions`.
+pub async fn process_regions(app: &AppHandle, base64_image: &str, format: Option<CaptureFormat>, regions: &[CropRegion]) -> Result<Vec<RegionOutcome>, Box<dyn Error + Send + Sync>> {
+    let image = Arc::new(crop::decode_image_as(base64_image, format)?);
+    let layout = Arc::new(localize::locate(app, image.clone()).await);
+    process_decoded_regions(app, image, layout, regions).await
+}
+
+/// Same as `process_regions` for an image that is already decoded and localized
+pub async fn process_decoded_regions(app: &AppHandle, image: Arc<DynamicImage>, layout: Arc<RegionLayout>, regions: &[CropRegion]) -> Result<Vec<RegionOutcome>, Box<dyn Error + Send + Sync>> {
+    let permits = Arc::new(Semaphore::new(worker_count()));
+
+    let mut tasks = Vec::new();
+    for &region in regions {
+        let app = app.clone();
+        let image = image.clone();
+        let layout = layout.clone();
+        let permits = permits.clone();
+        tasks.push(tokio::spawn(run_region(app, image, layout, region, permits)));
+    }
+
+    // Await in submission order so results stay deterministic regardless of completion order
+    let mut outcomes = Vec::with_capacity(tasks.len());
+    for task in tasks {
+        outcomes.push(task.await??);
+    }
+
+    Ok(outcomes)
+}
+
+async fn run_region(app: AppHandle, image: Arc<DynamicImage>, layout: Arc<RegionLayout>, region: CropRegion, permits: Arc<Semaphore>) -> Result<RegionOutcome, Box<dyn Error + Send + Sync>> {
+    let _permit = permits.acquire_owned().await?;
+    tokio::task::spawn_blocking(move || process_region(&app, &image, &layout, region)).await?
+}
+
+fn process_region(app: &AppHandle, image: &DynamicImage, layout: &RegionLayout, region: CropRegion) -> Result<RegionOutcome, Box<dyn Error + Send + Sync>> {
+    let crop_start = Instant::now();
+    let rect = layout.rect(image.width(), image.height(), region);
+    let cropped = crop::crop_rect(image, rect);
+    let crop = preprocess::apply_all(cropped, &crop::region_preprocess(app, region));
+    let crop_time = crop_start.elapsed();
+
+    let ocr_start = Instant::now();
+    let result = ocr::backend_for(app, region).recognize(&crop)?;
+    let ocr_time = ocr_start.elapsed();
+
+    if let Err(e) = app.emit("ocr-complete", result.line_texts()) {
+        warn!("Failed to emit OCR complete event: {}", e);
+    }
+
+    Ok(RegionOutcome { region, rect, crop, result, crop_time, ocr_time })
+}
+
+/// Output of the ocrs and Tesseract backends for the same crop
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct BackendComparison {
+    pub region: CropRegion,
+    pub ocrs: Option<OcrResult>,
+    pub tesseract: Option<OcrResult>,
+    pub errors: Vec<String>,
+    pub agree: bool,
+}
+
+/// Runs both backends on the same preprocessed crops and reports where they disagree
+pub async fn compare_backends(app: &AppHandle, base64_image: &str, format: Option<CaptureFormat>, regions: &[CropRegion]) -> Result<Vec<BackendComparison>, Box<dyn Error + Send + Sync>> {
+    let image = Arc::new(crop::decode_image_as(base64_image, format)?);
+    let layout = Arc::new(localize::locate(app, image.clone()).await);
+    let permits = Arc::new(Semaphore::new(worker_count()));
+
+    let mut tasks = Vec::new();
+    for &region in regions {
+        let app = app.clone();
+        let image = image.clone();
+        let layout = layout.clone();
+        let permits = permits.clone();
+        tasks.push(tokio::spawn(async move {
+            let _permit = permits.acquire_owned().await?;
+            let comparison = tokio::task::spawn_blocking(move || compare_region(&app, &image, &layout, region)).await?;
+            Ok::<_, Box<dyn Error + Send + Sync>>(comparison)
+        }));
+    }
+
+    let mut comparisons = Vec::with_capacity(tasks.len());
+    for task in tasks {
+        comparisons.push(task.await??);
+    }
+
+    Ok(comparisons)
+}
+
+fn compare_region(app: &AppHandle, image: &DynamicImage, layout: &RegionLayout, region: CropRegion) -> BackendComparison {
+    let cropped = crop::crop_rect(image, layout.rect(image.width(), image.height(), region));
+    let crop = preprocess::apply_all(cropped, &crop::region_preprocess(app, region));
+
+    // Keep the region's whitelist when Tesseract is already configured for it
+    let tesseract_config = match BackendConfig::for_region(app, region) {
+        config @ BackendConfig::Tesseract { .. } => config,
+        BackendConfig::Ocrs => BackendConfig::Tesseract { whitelist: None },
+    };
+
+    let mut errors = Vec::new();
+    let mut run = |backend: Box<dyn OcrBackend>| match backend.recognize(&crop) {
+        Ok(result) => Some(result),
+        Err(e) => {
+            errors.push(format!("{}: {}", backend.name(), e));
+            None
+        }
+    };
+    let ocrs = run(BackendConfig::Ocrs.build(app, None));
+    let tesseract = run(tesseract_config.build(app, ocr::tesseract_language(app)));
+
+    let agree = match (&ocrs, &tesseract) {
+        (Some(a), Some(b)) => normalize_text(&a.text()) == normalize_text(&b.text()),
+        _ => false,
+    };
+
+    BackendComparison { region, ocrs, tesseract, errors, agree }
+}
+
+/// Lowercases and collapses whitespace so formatting differences don't count as disagreement
+fn normalize_text(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
@@ -0,0 +1,118 @@
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use image::{DynamicImage, ImageError, imageops::FilterType};
+use std::io::Cursor;
+
+/// A single image operation applied to a crop before OCR
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum PreprocessStep {
+    /// Converts the image to 8-bit luma
+    Grayscale,
+    /// Inverts every pixel, useful for light text on a dark background
+    Invert,
+    /// Adjusts contrast, negative values decrease it
+    Contrast { amount: f32 },
+    /// Binarizes the image against a fixed luma level
+    Threshold { level: u8 },
+    /// Binarizes each pixel against the mean of its surrounding block
+    AdaptiveThreshold { block_radius: u32 },
+    /// Applies an unsharp mask
+    Sharpen { sigma: f32, threshold: i32 },
+    /// Scales the image up, OCR handles small glyphs poorly
+    Upscale { factor: f32 },
+}
+
+impl PreprocessStep {
+    pub fn apply(&self, img: DynamicImage) -> DynamicImage {
+        match *self {
+            PreprocessStep::Grayscale => DynamicImage::ImageLuma8(img.to_luma8()),
+            PreprocessStep::Invert => {
+                let mut inverted = img;
+                inverted.invert();
+                inverted
+            }
+            PreprocessStep::Contrast { amount } => img.adjust_contrast(amount),
+            PreprocessStep::Threshold { level } => {
+                let mut luma = img.to_luma8();
+                for pixel in luma.pixels_mut() {
+                    pixel.0[0] = if pixel.0[0] >= level { 255 } else { 0 };
+                }
+                DynamicImage::ImageLuma8(luma)
+            }
+            PreprocessStep::AdaptiveThreshold { block_radius } => {
+                let luma = img.to_luma8();
+                DynamicImage::ImageLuma8(imageproc::contrast::adaptive_threshold(&luma, block_radius.max(1)))
+            }
+            PreprocessStep::Sharpen { sigma, threshold } => img.unsharpen(sigma, threshold),
+            PreprocessStep::Upscale { factor } => {
+                if factor <= 1.0 {
+                    return img;
+                }
+                let width = (img.width() as f32 * factor).round() as u32;
+                let height = (img.height() as f32 * factor).round() as u32;
+                img.resize_exact(width, height, FilterType::CatmullRom)
+            }
+        }
+    }
+}
+
+/// Runs every step of a chain in order
+pub fn apply_all(img: DynamicImage, steps: &[PreprocessStep]) -> DynamicImage {
+    steps.iter().fold(img, |current, step| step.apply(current))
+}
+
+/// Output of one preprocessing step, `step` is `None` for the untouched crop
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct StepPreview {
+    pub step: Option<PreprocessStep>,
+    pub image: String,
+}
+
+/// Runs a chain and returns the intermediate image after every step as base64 PNG
+pub fn preview_steps(img: DynamicImage, steps: &[PreprocessStep]) -> Result<Vec<StepPreview>, ImageError> {
+    let mut previews = vec![StepPreview { step: None, image: encode_png(&img)? }];
+
+    let mut current = img;
+    for step in steps {
+        current = step.apply(current);
+        previews.push(StepPreview { step: Some(*step), image: encode_png(&current)? });
+    }
+
+    Ok(previews)
+}
+
+/// Parses a chain from its JSON settings value
+pub fn parse_steps(json: &str) -> Result<Vec<PreprocessStep>, serde_json::Error> {
+    serde_json::from_str(json)
+}
+
+/// Encodes an image as base64 PNG for previews
+pub fn encode_png(img: &DynamicImage) -> Result<String, ImageError> {
+    let mut buffer = Cursor::new(Vec::new());
+    img.write_to(&mut buffer, image::ImageFormat::Png)?;
+    Ok(BASE64.encode(buffer.into_inner()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_steps() {
+        let steps = parse_steps(r#"[{"op":"grayscale"},{"op":"upscale","factor":2.0},{"op":"adaptive_threshold","block_radius":8}]"#).unwrap();
+        assert_eq!(steps, vec![
+            PreprocessStep::Grayscale,
+            PreprocessStep::Upscale { factor: 2.0 },
+            PreprocessStep::AdaptiveThreshold { block_radius: 8 },
+        ]);
+    }
+
+    #[test]
+    fn test_apply_all_upscales_and_inverts() {
+        let img = DynamicImage::ImageLuma8(image::GrayImage::from_pixel(10, 5, image::Luma([10])));
+        let result = apply_all(img, &[PreprocessStep::Upscale { factor: 2.0 }, PreprocessStep::Invert]);
+
+        assert_eq!((result.width(), result.height()), (20, 10));
+        assert_eq!(result.to_luma8().get_pixel(0, 0).0[0], 245);
+    }
+}
@@ -0,0 +1,357 @@
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use diesel::prelude::*;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageError, Rgba, RgbaImage};
+use std::error::Error;
+use std::sync::Arc;
+use tauri::AppHandle;
+use tracing::{info, warn};
+
+use crate::crop::{self, CropRegion, BASE_HEIGHT, BASE_WIDTH};
+use crate::db;
+use crate::encoding::{self, CaptureFormat};
+use crate::localize::{self, Rect, RegionLayout};
+use crate::models::screenshots;
+use crate::thumbnail;
+
+/// Margin added around every area in 1920x1080 pixels and scaled with the capture,
+/// so text that sits a little off the located region is still covered
+const PADDING: f32 = 16.0;
+
+/// Blurred areas are shrunk by this factor and scaled back up, leaving no readable text
+const BLUR_FACTOR: u32 = 16;
+
+/// Regions kept in `stats_only` mode, the summary heading is needed to detect the screen
+const STAT_REGIONS: [CropRegion; 5] = [
+    CropRegion::MissionSummary,
+    CropRegion::SummaryFirst,
+    CropRegion::SummarySecond,
+    CropRegion::SummaryThird,
+    CropRegion::SummaryFourth,
+];
+
+/// What is removed from captures before they are stored, from the `privacy_mode` setting
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PrivacyMode {
+    /// Captures are stored as taken
+    Off,
+    /// The areas in `privacy_regions` are hidden, a hidden username can't be read back by OCR
+    Redact,
+    /// Everything but the stat cards and the summary heading is blacked out
+    StatsOnly,
+}
+
+impl PrivacyMode {
+    pub fn parse(value: &str) -> Option<PrivacyMode> {
+        match value.trim().to_lowercase().as_str() {
+            "off" => Some(PrivacyMode::Off),
+            "redact" => Some(PrivacyMode::Redact),
+            "stats_only" => Some(PrivacyMode::StatsOnly),
+            _ => None,
+        }
+    }
+}
+
+/// How redacted areas are hidden, from the `privacy_style` setting
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RedactStyle {
+    Black,
+    Blur,
+}
+
+impl RedactStyle {
+    pub fn parse(value: &str) -> Option<RedactStyle> {
+        match value.trim().to_lowercase().as_str() {
+            "black" => Some(RedactStyle::Black),
+            "blur" => Some(RedactStyle::Blur),
+            _ => None,
+        }
+    }
+}
+
+/// An area to hide, a region name or an `x,y,width,height` rectangle in 1920x1080 pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RedactArea {
+    Region(CropRegion),
+    Rect(Rect),
+}
+
+impl RedactArea {
+    pub fn parse(value: &str) -> Option<RedactArea> {
+        let value = value.trim();
+        if let Some(region) = crop::region_from_name(value) {
+            return Some(RedactArea::Region(region));
+        }
+
+        let numbers = value
+            .split(',')
+            .map(|part| part.trim().parse::<u32>().ok())
+            .collect::<Option<Vec<u32>>>()?;
+        match numbers[..] {
+            [x, y, width, height] if width > 0 && height > 0 => Some(RedactArea::Rect((x, y, width, height))),
+            _ => None,
+        }
+    }
+}
+
+/// The configured privacy settings
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Privacy {
+    pub mode: PrivacyMode,
+    pub style: RedactStyle,
+    pub areas: Vec<RedactArea>,
+}
+
+impl Privacy {
+    /// Reads `privacy_mode`, `privacy_style` and `privacy_regions`, the last being `;` separated.
+    /// Invalid values are logged and left out so a typo never stores an unredacted capture.
+    pub async fn configured(app: &AppHandle) -> Privacy {
+        let mode = match db::setting(app, "privacy_mode").await {
+            Some(value) => PrivacyMode::parse(&value).unwrap_or_else(|| {
+                warn!("Invalid privacy_mode setting {:?}, keeping only the stats", value);
+                PrivacyMode::StatsOnly
+            }),
+            None => PrivacyMode::Off,
+        };
+        let style = db::setting(app, "privacy_style")
+            .await
+            .and_then(|v| RedactStyle::parse(&v))
+            .unwrap_or(RedactStyle::Black);
+        let areas = db::setting(app, "privacy_regions")
+            .await
+            .map(|value| parse_areas(&value))
+            .unwrap_or_else(|| vec![RedactArea::Region(CropRegion::SummaryUsername)]);
+
+        Privacy { mode, style, areas }
+    }
+
+    pub fn is_active(&self) -> bool {
+        match self.mode {
+            PrivacyMode::Off => false,
+            PrivacyMode::Redact => !self.areas.is_empty(),
+            PrivacyMode::StatsOnly => true,
+        }
+    }
+
+    /// Hides the configured areas, or everything outside the stat regions
+    pub fn apply(&self, img: &DynamicImage, layout: &RegionLayout) -> DynamicImage {
+        let (width, height) = (img.width(), img.height());
+        let rect_of = |area: &RedactArea| {
+            let rect = match *area {
+                RedactArea::Region(region) => layout.rect(width, height, region),
+                RedactArea::Rect(rect) => scale_rect(rect, width, height),
+            };
+            pad_rect(rect, width, height)
+        };
+
+        match self.mode {
+            PrivacyMode::Off => img.clone(),
+            PrivacyMode::Redact => {
+                let mut rgba = img.to_rgba8();
+                for area in &self.areas {
+                    hide(&mut rgba, rect_of(area), self.style);
+                }
+                DynamicImage::ImageRgba8(rgba)
+            }
+            PrivacyMode::StatsOnly => {
+                let source = img.to_rgba8();
+                let mut rgba = RgbaImage::from_pixel(width, height, Rgba([0, 0, 0, 255]));
+                for region in STAT_REGIONS {
+                    let (x, y, w, h) = rect_of(&RedactArea::Region(region));
+                    image::imageops::replace(&mut rgba, &*image::imageops::crop_imm(&source, x, y, w, h), x as i64, y as i64);
+                }
+                DynamicImage::ImageRgba8(rgba)
+            }
+        }
+    }
+}
+
+fn parse_areas(value: &str) -> Vec<RedactArea> {
+    value
+        .split(';')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .filter_map(|entry| {
+            let area = RedactArea::parse(entry);
+            if area.is_none() {
+                warn!("Ignoring invalid privacy region {:?}", entry);
+            }
+            area
+        })
+        .collect()
+}
+
+/// Converts a rectangle from the 1920x1080 layout to capture pixels
+fn scale_rect((x, y, width, height): Rect, capture_width: u32, capture_height: u32) -> Rect {
+    let scale_x = capture_width as f32 / BASE_WIDTH as f32;
+    let scale_y = capture_height as f32 / BASE_HEIGHT as f32;
+    (
+        (x as f32 * scale_x).round() as u32,
+        (y as f32 * scale_y).round() as u32,
+        (width as f32 * scale_x).round() as u32,
+        (height as f32 * scale_y).round() as u32,
+    )
+}
+
+/// Grows a rectangle by the padding and clamps it to the capture
+fn pad_rect((x, y, width, height): Rect, capture_width: u32, capture_height: u32) -> Rect {
+    let padding_x = (PADDING * capture_width as f32 / BASE_WIDTH as f32).round() as u32;
+    let padding_y = (PADDING * capture_height as f32 / BASE_HEIGHT as f32).round() as u32;
+    let left = x.saturating_sub(padding_x).min(capture_width);
+    let top = y.saturating_sub(padding_y).min(capture_height);
+    let right = (x + width + padding_x).min(capture_width);
+    let bottom = (y + height + padding_y).min(capture_height);
+    (left, top, right.saturating_sub(left), bottom.saturating_sub(top))
+}
+
+/// Blacks out or blurs one rectangle of an image
+pub fn hide(img: &mut RgbaImage, (x, y, width, height): Rect, style: RedactStyle) {
+    if width == 0 || height == 0 {
+        return;
+    }
+
+    let covered = match style {
+        RedactStyle::Black => RgbaImage::from_pixel(width, height, Rgba([0, 0, 0, 255])),
+        RedactStyle::Blur => {
+            let area = image::imageops::crop_imm(img, x, y, width, height).to_image();
+            let small = image::imageops::resize(&area, (width / BLUR_FACTOR).max(1), (height / BLUR_FACTOR).max(1), FilterType::Triangle);
+            image::imageops::resize(&small, width, height, FilterType::Triangle)
+        }
+    };
+    image::imageops::replace(img, &covered, x as i64, y as i64);
+}
+
+/// Applies privacy settings to freshly captured image data, `img` being the decoded `data`.
+/// Runs before anything is written so debug copies, the database, previews, webhooks
+/// and diagnostics bundles only ever see the redacted capture. The capture must already be
+/// normalized to the game viewport and `layout` located on it, so areas are hidden where
+/// the regions actually are in windowed or shifted captures.
+pub fn redact_capture(privacy: &Privacy, img: &DynamicImage, layout: &RegionLayout, data: Vec<u8>, format: CaptureFormat) -> Result<Vec<u8>, ImageError> {
+    if !privacy.is_active() {
+        return Ok(data);
+    }
+
+    format.encode(&privacy.apply(img, layout))
+}
+
+/// Applies the current privacy settings to every stored screenshot and re-renders its previews.
+/// Returns the number of screenshots rewritten, failures are logged and skipped.
+pub async fn redact_stored(app: &AppHandle) -> Result<usize, Box<dyn Error + Send + Sync>> {
+    let privacy = Privacy::configured(app).await;
+    if !privacy.is_active() {
+        return Ok(0);
+    }
+
+    let ids: Vec<i32> = db::run(app, |conn| screenshots::table.select(screenshots::id).order(screenshots::id.asc()).load(conn)).await?;
+    let mut redacted = 0;
+    for screenshot_id in ids {
+        match redact_one(app, &privacy, screenshot_id).await {
+            Ok(()) => redacted += 1,
+            Err(e) => warn!("Failed to redact screenshot {}: {}", screenshot_id, e),
+        }
+    }
+
+    info!("Redacted {} stored screenshots", redacted);
+    Ok(redacted)
+}
+
+async fn redact_one(app: &AppHandle, privacy: &Privacy, screenshot_id: i32) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let (image_data, image_format): (String, Option<String>) = db::run(app, move |conn| {
+        screenshots::table
+            .filter(screenshots::id.eq(screenshot_id))
+            .select((screenshots::image, screenshots::image_format))
+            .first(conn)
+    })
+    .await?;
+
+    // Older rows without a stored format were JPEG, re-encoding keeps the stored format
+    let format = image_format.as_deref().and_then(CaptureFormat::parse).unwrap_or_default();
+    let (data, image) = tokio::task::spawn_blocking(move || {
+        let data = BASE64.decode(&image_data)?;
+        let image = encoding::decode(&data, Some(format))?;
+        Ok::<_, Box<dyn Error + Send + Sync>>((data, Arc::new(image)))
+    })
+    .await??;
+    let layout = localize::locate(app, image.clone()).await;
+
+    let privacy = privacy.clone();
+    let (image_data, previews) = tokio::task::spawn_blocking(move || {
+        let data = redact_capture(&privacy, &image, &layout, data, format)?;
+        let image_data = BASE64.encode(data);
+        let previews = thumbnail::render(&image_data, Some(format))?;
+        Ok::<_, Box<dyn Error + Send + Sync>>((image_data, previews))
+    })
+    .await??;
+
+    db::run(app, move |conn| {
+        diesel::update(screenshots::table.filter(screenshots::id.eq(screenshot_id)))
+            .set((screenshots::image.eq(image_data), screenshots::image_format.eq(format.name())))
+            .execute(conn)?;
+        db::save_previews(conn, screenshot_id, &previews)
+    })
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn white() -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_pixel(1920, 1080, Rgba([255, 255, 255, 255])))
+    }
+
+    #[test]
+    fn test_parse_areas() {
+        assert_eq!(
+            parse_areas("SummaryUsername; 1200,80,400,48 ;nope;1,2,0,4"),
+            vec![RedactArea::Region(CropRegion::SummaryUsername), RedactArea::Rect((1200, 80, 400, 48))]
+        );
+    }
+
+    #[test]
+    fn test_redact_and_stats_only() {
+        let layout = RegionLayout::fixed();
+        let username = crop::region_rect(1920, 1080, CropRegion::SummaryUsername);
+        let card = crop::region_rect(1920, 1080, CropRegion::SummaryFirst);
+
+        let privacy = Privacy { mode: PrivacyMode::Redact, style: RedactStyle::Black, areas: vec![RedactArea::Region(CropRegion::SummaryUsername)] };
+        let redacted = privacy.apply(&white(), &layout).to_rgba8();
+        assert_eq!(redacted.get_pixel(username.0, username.1), &Rgba([0, 0, 0, 255]));
+        assert_eq!(redacted.get_pixel(card.0, card.1), &Rgba([255, 255, 255, 255]));
+
+        let privacy = Privacy { mode: PrivacyMode::StatsOnly, ..privacy };
+        let stats_only = privacy.apply(&white(), &layout).to_rgba8();
+        assert_eq!(stats_only.get_pixel(username.0 + username.2 / 2, username.1 + username.3 / 2), &Rgba([0, 0, 0, 255]));
+        assert_eq!(stats_only.get_pixel(card.0, card.1), &Rgba([255, 255, 255, 255]));
+        assert_eq!(stats_only.get_pixel(1900, 10), &Rgba([0, 0, 0, 255]));
+    }
+
+    #[test]
+    fn test_redact_follows_shifted_layout() {
+        // A windowed capture where localization found the username card further down and right
+        let (width, height) = (1600, 900);
+        let fixed = crop::region_rect(width, height, CropRegion::SummaryUsername);
+        let shifted = (fixed.0 + 120, fixed.1 + 80, fixed.2, fixed.3);
+        let layout = RegionLayout::from_rects([(CropRegion::SummaryUsername, shifted)].into_iter().collect());
+
+        let capture = DynamicImage::ImageRgba8(RgbaImage::from_pixel(width, height, Rgba([255, 255, 255, 255])));
+        let data = CaptureFormat::Png.encode(&capture).unwrap();
+        let privacy = Privacy { mode: PrivacyMode::Redact, style: RedactStyle::Black, areas: vec![RedactArea::Region(CropRegion::SummaryUsername)] };
+        let redacted = encoding::decode(&redact_capture(&privacy, &capture, &layout, data, CaptureFormat::Png).unwrap(), Some(CaptureFormat::Png))
+            .unwrap()
+            .to_rgba8();
+
+        assert_eq!(redacted.get_pixel(shifted.0 + shifted.2 - 1, shifted.1 + shifted.3 - 1), &Rgba([0, 0, 0, 255]));
+        assert_eq!(redacted.get_pixel(fixed.0, fixed.1), &Rgba([255, 255, 255, 255]));
+    }
+
+    #[test]
+    fn test_padding_scales_with_capture() {
+        assert_eq!(pad_rect((100, 100, 10, 10), 1920, 1080), (84, 84, 42, 42));
+        assert_eq!(pad_rect((100, 100, 10, 10), 3840, 2160), (68, 68, 74, 74));
+        assert_eq!(pad_rect((100, 100, 10, 10), 1920, 1200), (84, 82, 42, 46));
+    }
+}
@@ -0,0 +1,345 @@
+use diesel::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::{AppHandle, Emitter, Manager, path::BaseDirectory};
+use tracing::{debug, error, info, warn, Instrument};
+
+use crate::crop::CropRegion;
+use crate::debug::DebugJob;
+use crate::encoding::CaptureFormat;
+use crate::error::{AppError, ErrorCode};
+use crate::fields::{self, ExtractedField};
+use crate::labels::{self, GameLanguage};
+use crate::models::{screenshots, MatchFieldRecord};
+use crate::pipeline;
+
+/// Regions recognized for a stored summary screenshot
+pub const SUMMARY_REGIONS: [CropRegion; 5] = [
+    CropRegion::SummaryFirst,
+    CropRegion::SummarySecond,
+    CropRegion::SummaryThird,
+    CropRegion::SummaryFourth,
+    CropRegion::SummaryUsername,
+];
+
+static RUNNING: AtomicBool = AtomicBool::new(false);
+static CANCEL: AtomicBool = AtomicBool::new(false);
+
+/// Progress event emitted after every screenshot as `reprocess-progress`
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ReprocessProgress {
+    pub screenshot_id: i32,
+    pub done: usize,
+    pub total: usize,
+    pub error: Option<String>,
+}
+
+/// A field whose OCR value changed during a run
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct FieldDiff {
+    pub screenshot_id: i32,
+    pub region: String,
+    pub before: Option<String>,
+    pub after: String,
+    /// A manual correction exists, so the displayed value is unchanged
+    pub manual: bool,
+}
+
+/// Summary of a run, written to `reports/` and emitted as `reprocess-complete`
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ReprocessReport {
+    pub started_at: String,
+    pub finished_at: String,
+    pub total: usize,
+    pub processed: usize,
+    pub failed: Vec<(i32, String)>,
+    pub cancelled: bool,
+    pub diffs: Vec<FieldDiff>,
+    pub path: Option<String>,
+}
+
+/// Summary columns replaced by a run, `None` leaves the stored text untouched
+#[derive(AsChangeset)]
+#[diesel(table_name = screenshots)]
+struct SummaryColumns {
+    summary_first: Option<String>,
+    summary_second: Option<String>,
+    summary_third: Option<String>,
+    summary_fourth: Option<String>,
+    summary_username: Option<String>,
+}
+
+/// Crops and recognizes a stored screenshot with the current pipeline and saves the result.
+/// Manual corrections are kept, only the OCR columns are replaced. The summary text of a
+/// manually corrected region also stays as it was.
+pub async fn recognize_screenshot(app: &AppHandle, screenshot_id: i32) -> Result<Vec<ExtractedField>, Box<dyn Error + Send + Sync>> {
+    let (screenshot_data, language_code, image_format): (String, Option<String>, Option<String>) = crate::db::run(app, move |conn| {
+        screenshots::table
+            .filter(screenshots::id.eq(screenshot_id))
+            .select((screenshots::image, screenshots::language, screenshots::image_format))
+            .first(conn)
+    })
+    .await?;
+    let format = image_format.as_deref().and_then(CaptureFormat::parse);
+    let game_language = match language_code.as_deref().and_then(GameLanguage::from_code) {
+        Some(language) => Some(language),
+        None => labels::configured_language(app).await,
+    };
+
+    info!("Processing screenshot ID: {}", screenshot_id);
+    let mut job = DebugJob::start(app, &screenshot_data).await;
+
+    // Crop and recognize all regions in parallel, results come back in region order
+    let outcomes = pipeline::process_regions(app, &screenshot_data, format, &SUMMARY_REGIONS).await?;
+
+    let mut region_results = Vec::new();
+    let mut extracted_fields = Vec::new();
+    let mut summaries: HashMap<CropRegion, String> = HashMap::new();
+
+    for outcome in outcomes {
+        if let Some(job) = job.as_mut() {
+            job.record_region(outcome.region, outcome.rect, &outcome.crop, &outcome.result, outcome.crop_time, outcome.ocr_time);
+        }
+
+        let result_text = outcome.result.text();
+        debug!("OCR Results for {:?}: {}", outcome.region, result_text);
+        summaries.insert(outcome.region, result_text);
+        extracted_fields.push(fields::extract_field(outcome.region, &outcome.result, game_language));
+        region_results.push((outcome.region, outcome.result));
+    }
+
+    if let Some(job) = job {
+        job.finish();
+    }
+
+    let fields_to_save = extracted_fields.clone();
+
+    crate::db::run(app, move |conn| {
+        // Corrections are created from the summary text, so regions with a manual value keep theirs
+        let manual: HashSet<String> = crate::db::get_match_fields(conn, screenshot_id)?
+            .into_iter()
+            .filter(|field| field.manual_value.is_some())
+            .map(|field| field.region)
+            .collect();
+        let mut summary = |region: CropRegion| {
+            let text = summaries.remove(&region).unwrap_or_default();
+            (!manual.contains(&format!("{:?}", region))).then_some(text)
+        };
+        let columns = SummaryColumns {
+            summary_first: summary(CropRegion::SummaryFirst),
+            summary_second: summary(CropRegion::SummarySecond),
+            summary_third: summary(CropRegion::SummaryThird),
+            summary_fourth: summary(CropRegion::SummaryFourth),
+            summary_username: summary(CropRegion::SummaryUsername),
+        };
+
+        diesel::update(screenshots::table.filter(screenshots::id.eq(screenshot_id)))
+            .set((screenshots::recognized.eq(true), screenshots::ocr.eq(true), &columns))
+            .execute(conn)?;
+
+        // Keep the full OCR geometry of each region next to the flattened text
+        for (region, ocr_result) in &region_results {
+            crate::db::save_ocr_result(conn, screenshot_id, *region, ocr_result)?;
+        }
+        crate::db::save_match_fields(conn, screenshot_id, &fields_to_save)?;
+        Ok::<_, Box<dyn Error + Send + Sync>>(())
+    })
+    .await?;
+
+    // The local API and the overlay follow stored results through this event
+    let _ = app.emit("match-recognized", (screenshot_id, &extracted_fields));
+
+    Ok(extracted_fields)
+}
+
+/// Whether a re-process run is in progress
+pub fn is_running() -> bool {
+    RUNNING.load(Ordering::SeqCst)
+}
+
+/// Asks the running job to stop after the current screenshot
+pub fn cancel() {
+    CANCEL.store(true, Ordering::SeqCst);
+}
+
+/// Starts re-processing the given screenshots, or every stored one, in the background
+pub async fn start(app: &AppHandle, ids: Option<Vec<i32>>) -> Result<usize, Box<dyn Error + Send + Sync>> {
+    if RUNNING.swap(true, Ordering::SeqCst) {
+        return Err(AppError::new(ErrorCode::Busy, "A re-process run is already in progress").into());
+    }
+    CANCEL.store(false, Ordering::SeqCst);
+
+    let ids = match ids {
+        Some(ids) => ids,
+        None => match stored_ids(app).await {
+            Ok(ids) => ids,
+            Err(e) => {
+                RUNNING.store(false, Ordering::SeqCst);
+                return Err(e);
+            }
+        },
+    };
+    let total = ids.len();
+
+    let handle = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let report = run(&handle, ids).await;
+        RUNNING.store(false, Ordering::SeqCst);
+
+        info!(
+            "Re-processed {} of {} screenshots, {} failed, {} fields changed",
+            report.processed,
+            report.total,
+            report.failed.len(),
+            report.diffs.len()
+        );
+        if let Err(e) = handle.emit("reprocess-complete", &report) {
+            warn!("Failed to emit reprocess complete event: {}", e);
+        }
+    });
+
+    Ok(total)
+}
+
+async fn stored_ids(app: &AppHandle) -> Result<Vec<i32>, Box<dyn Error + Send + Sync>> {
+    let ids = crate::db::run(app, |conn| {
+        screenshots::table
+            .filter(screenshots::recognized.eq(true))
+            .select(screenshots::id)
+            .order(screenshots::id.asc())
+            .load(conn)
+    })
+    .await?;
+    Ok(ids)
+}
+
+async fn load_fields(app: &AppHandle, screenshot_id: i32) -> Result<Vec<MatchFieldRecord>, Box<dyn Error + Send + Sync>> {
+    Ok(crate::db::run(app, move |conn| crate::db::get_match_fields(conn, screenshot_id)).await?)
+}
+
+async fn run(app: &AppHandle, ids: Vec<i32>) -> ReprocessReport {
+    let mut report = ReprocessReport {
+        started_at: chrono::Local::now().to_rfc3339(),
+        finished_at: String::new(),
+        total: ids.len(),
+        processed: 0,
+        failed: Vec::new(),
+        cancelled: false,
+        diffs: Vec::new(),
+        path: None,
+    };
+
+    for (index, screenshot_id) in ids.into_iter().enumerate() {
+        if CANCEL.load(Ordering::SeqCst) {
+            report.cancelled = true;
+            break;
+        }
+
+        let result = reprocess_one(app, screenshot_id)
+            .instrument(tracing::info_span!("reprocess", screenshot_id))
+            .await;
+        let error = match result {
+            Ok(diffs) => {
+                report.processed += 1;
+                report.diffs.extend(diffs);
+                None
+            }
+            Err(e) => {
+                error!("Error re-processing screenshot {}: {}", screenshot_id, e);
+                report.failed.push((screenshot_id, e.to_string()));
+                Some(e.to_string())
+            }
+        };
+
+        let progress = ReprocessProgress { screenshot_id, done: index + 1, total: report.total, error };
+        let _ = app.emit("reprocess-progress", &progress);
+    }
+
+    report.finished_at = chrono::Local::now().to_rfc3339();
+    match write_report(app, &report) {
+        Ok(path) => report.path = Some(path.to_string_lossy().into_owned()),
+        Err(e) => error!("Failed to write reprocess report: {}", e),
+    }
+
+    report
+}
+
+async fn reprocess_one(app: &AppHandle, screenshot_id: i32) -> Result<Vec<FieldDiff>, Box<dyn Error + Send + Sync>> {
+    let before = load_fields(app, screenshot_id).await?;
+    recognize_screenshot(app, screenshot_id).await?;
+    let after = load_fields(app, screenshot_id).await?;
+
+    Ok(diff_fields(screenshot_id, &before, &after))
+}
+
+/// Compares the OCR values of a screenshot's fields before and after a run
+fn diff_fields(screenshot_id: i32, before: &[MatchFieldRecord], after: &[MatchFieldRecord]) -> Vec<FieldDiff> {
+    after
+        .iter()
+        .filter_map(|field| {
+            let previous = before.iter().find(|old| old.region == field.region);
+            if previous.is_some_and(|old| old.value == field.value) {
+                return None;
+            }
+
+            Some(FieldDiff {
+                screenshot_id,
+                region: field.region.clone(),
+                before: previous.map(|old| old.value.clone()),
+                after: field.value.clone(),
+                manual: field.manual_value.is_some(),
+            })
+        })
+        .collect()
+}
+
+fn write_report(app: &AppHandle, report: &ReprocessReport) -> Result<PathBuf, Box<dyn Error + Send + Sync>> {
+    let dir = app.path().resolve("reports", BaseDirectory::AppData)?;
+    std::fs::create_dir_all(&dir)?;
+
+    let path = dir.join(format!("reprocess_{}.json", chrono::Local::now().format("%Y%m%d_%H%M%S")));
+    std::fs::write(&path, serde_json::to_string_pretty(report)?)?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(region: &str, value: &str, manual_value: Option<&str>) -> MatchFieldRecord {
+        MatchFieldRecord {
+            id: 1,
+            screenshot_id: 1,
+            region: region.to_string(),
+            label: None,
+            raw_text: value.to_string(),
+            value: value.to_string(),
+            number: None,
+            corrected: false,
+            suspicious: false,
+            manual_value: manual_value.map(str::to_string),
+            manual_number: None,
+            manual_label: None,
+        }
+    }
+
+    #[test]
+    fn test_diff_fields() {
+        let before = vec![field("SummaryFirst", "2", None), field("SummarySecond", "7", Some("1"))];
+        let after = vec![
+            field("SummaryFirst", "2", None),
+            field("SummarySecond", "1", Some("1")),
+            field("SummaryUsername", "hunter", None),
+        ];
+
+        let diffs = diff_fields(5, &before, &after);
+
+        assert_eq!(diffs.len(), 2);
+        assert_eq!(diffs[0].region, "SummarySecond");
+        assert_eq!(diffs[0].before.as_deref(), Some("7"));
+        assert!(diffs[0].manual);
+        assert_eq!(diffs[1].before, None);
+    }
+}
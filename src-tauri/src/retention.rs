@@ -0,0 +1,297 @@
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Manager, path::BaseDirectory};
+use tracing::{error, info};
+
+use crate::db;
+
+/// How often the background maintenance task applies the retention policy
+const MAINTENANCE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Kinds of files Martini keeps on disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageCategory {
+    /// Full captures written by the shortcut handler (`screenshot_<ts>.<ext>`)
+    Captures,
+    /// Loose region crops left behind by older versions
+    Crops,
+    /// Per-job debug folders written when debug artifacts are enabled
+    Jobs,
+    /// The SQLite database holding stored matches
+    Database,
+}
+
+/// Disk usage of a single storage category
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct DiskUsage {
+    pub category: StorageCategory,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// Limits applied to each category of debug files, a value of 0 disables that limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    pub max_age_days: u64,
+    pub max_files: usize,
+    pub max_size_mb: u64,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        RetentionPolicy {
+            max_age_days: 7,
+            max_files: 200,
+            max_size_mb: 500,
+        }
+    }
+}
+
+impl RetentionPolicy {
+    /// Loads the policy from the settings table, falling back to defaults for missing values
+    pub fn load(app: &AppHandle) -> Self {
+        let defaults = RetentionPolicy::default();
+        let read = |setting_key: &str| db::read_setting(app, setting_key).and_then(|v| v.trim().parse::<u64>().ok());
+
+        RetentionPolicy {
+            max_age_days: read("retention_max_age_days").unwrap_or(defaults.max_age_days),
+            max_files: read("retention_max_files").map(|v| v as usize).unwrap_or(defaults.max_files),
+            max_size_mb: read("retention_max_size_mb").unwrap_or(defaults.max_size_mb),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct StoredFile {
+    path: PathBuf,
+    bytes: u64,
+    modified: SystemTime,
+}
+
+/// Starts the background task that periodically applies the retention policy
+pub fn start_maintenance(app: &AppHandle) {
+    let handle = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(MAINTENANCE_INTERVAL);
+        loop {
+            interval.tick().await;
+
+            let task_handle = handle.clone();
+            let result = tokio::task::spawn_blocking(move || apply_retention(&task_handle)).await;
+            match result {
+                Ok(Ok(removed)) if removed > 0 => info!("Retention removed {} debug files", removed),
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => error!("Error applying retention policy: {}", e),
+                Err(e) => error!("Retention task failed: {}", e),
+            }
+        }
+    });
+}
+
+/// Applies the configured retention policy to every file category, returns the number of removed files
+pub fn apply_retention(app: &AppHandle) -> Result<usize, Box<dyn Error + Send + Sync>> {
+    let policy = RetentionPolicy::load(app);
+    let debug_path = debug_images_dir(app)?;
+    let now = SystemTime::now();
+    let mut removed = 0;
+
+    for category in [StorageCategory::Captures, StorageCategory::Crops, StorageCategory::Jobs] {
+        let files = list_category(&debug_path, category)?;
+        for file in select_expired(files, &policy, now) {
+            let result = if file.path.is_dir() {
+                std::fs::remove_dir_all(&file.path)
+            } else {
+                std::fs::remove_file(&file.path)
+            };
+            match result {
+                Ok(()) => removed += 1,
+                Err(e) => error!("Failed to remove {}: {}", file.path.display(), e),
+            }
+        }
+    }
+
+    Ok(removed)
+}
+
+/// Reports how much disk space each storage category uses
+pub fn disk_usage(app: &AppHandle) -> Result<Vec<DiskUsage>, Box<dyn Error + Send + Sync>> {
+    let debug_path = debug_images_dir(app)?;
+    let mut usage = Vec::new();
+
+    for category in [StorageCategory::Captures, StorageCategory::Crops, StorageCategory::Jobs] {
+        let files = list_category(&debug_path, category)?;
+        usage.push(DiskUsage {
+            category,
+            files: files.len(),
+            bytes: files.iter().map(|f| f.bytes).sum(),
+        });
+    }
+
+    // The database is kept alongside its WAL and journal files
+    let db_path = app.path().resolve("app.db", BaseDirectory::AppData)?;
+    let mut db_usage = DiskUsage { category: StorageCategory::Database, files: 0, bytes: 0 };
+    for suffix in ["", "-wal", "-shm", "-journal"] {
+        let path = PathBuf::from(format!("{}{}", db_path.display(), suffix));
+        if let Ok(metadata) = std::fs::metadata(&path) {
+            db_usage.files += 1;
+            db_usage.bytes += metadata.len();
+        }
+    }
+    usage.push(db_usage);
+
+    Ok(usage)
+}
+
+/// Paths of the newest files of a category, newest first
+pub fn recent_files(app: &AppHandle, category: StorageCategory, count: usize) -> Result<Vec<PathBuf>, Box<dyn Error + Send + Sync>> {
+    let mut files = list_category(&debug_images_dir(app)?, category)?;
+    files.sort_by(|a, b| b.modified.cmp(&a.modified));
+    Ok(files.into_iter().take(count).map(|file| file.path).collect())
+}
+
+fn debug_images_dir(app: &AppHandle) -> Result<PathBuf, Box<dyn Error + Send + Sync>> {
+    let debug_path = app.path().resolve("debug_images", BaseDirectory::AppData)?;
+    std::fs::create_dir_all(&debug_path)?;
+    Ok(debug_path)
+}
+
+fn categorize(file_name: &str) -> StorageCategory {
+    if file_name.starts_with("screenshot_") {
+        StorageCategory::Captures
+    } else {
+        StorageCategory::Crops
+    }
+}
+
+fn list_category(debug_path: &Path, category: StorageCategory) -> Result<Vec<StoredFile>, Box<dyn Error + Send + Sync>> {
+    if category == StorageCategory::Jobs {
+        return list_jobs(&debug_path.join("jobs"));
+    }
+
+    let mut files = Vec::new();
+
+    for entry in std::fs::read_dir(debug_path)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if !metadata.is_file() {
+            continue;
+        }
+
+        if categorize(&entry.file_name().to_string_lossy()) != category {
+            continue;
+        }
+
+        files.push(StoredFile {
+            path: entry.path(),
+            bytes: metadata.len(),
+            modified: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+        });
+    }
+
+    Ok(files)
+}
+
+fn list_jobs(jobs_path: &Path) -> Result<Vec<StoredFile>, Box<dyn Error + Send + Sync>> {
+    let mut jobs = Vec::new();
+    if !jobs_path.exists() {
+        return Ok(jobs);
+    }
+
+    for entry in std::fs::read_dir(jobs_path)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if !metadata.is_dir() {
+            continue;
+        }
+
+        jobs.push(StoredFile {
+            path: entry.path(),
+            bytes: dir_size(&entry.path()),
+            modified: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+        });
+    }
+
+    Ok(jobs)
+}
+
+fn dir_size(path: &Path) -> u64 {
+    std::fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter_map(|entry| entry.metadata().ok())
+                .filter(|metadata| metadata.is_file())
+                .map(|metadata| metadata.len())
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+/// Picks the files that fall outside the policy, keeping the newest files first
+fn select_expired(mut files: Vec<StoredFile>, policy: &RetentionPolicy, now: SystemTime) -> Vec<StoredFile> {
+    files.sort_by(|a, b| b.modified.cmp(&a.modified));
+
+    let max_age = Duration::from_secs(policy.max_age_days * 24 * 60 * 60);
+    let max_bytes = policy.max_size_mb * 1024 * 1024;
+    let mut kept_bytes = 0;
+    let mut expired = Vec::new();
+
+    for (index, file) in files.into_iter().enumerate() {
+        let age = now.duration_since(file.modified).unwrap_or_default();
+        let too_old = policy.max_age_days > 0 && age > max_age;
+        let too_many = policy.max_files > 0 && index >= policy.max_files;
+        let over_budget = policy.max_size_mb > 0 && kept_bytes + file.bytes > max_bytes;
+
+        if too_old || too_many || over_budget {
+            expired.push(file);
+        } else {
+            kept_bytes += file.bytes;
+        }
+    }
+
+    expired
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(name: &str, bytes: u64, age_secs: u64, now: SystemTime) -> StoredFile {
+        StoredFile {
+            path: PathBuf::from(name),
+            bytes,
+            modified: now - Duration::from_secs(age_secs),
+        }
+    }
+
+    #[test]
+    fn test_select_expired_by_age_count_and_size() {
+        let now = SystemTime::now();
+        let day = 24 * 60 * 60;
+        let files = vec![
+            file("old.jpg", 10, 10 * day, now),
+            file("a.jpg", 400 * 1024, 10, now),
+            file("b.jpg", 400 * 1024, 20, now),
+            file("c.jpg", 400 * 1024, 30, now),
+        ];
+        let policy = RetentionPolicy { max_age_days: 7, max_files: 3, max_size_mb: 1 };
+
+        let expired: Vec<_> = select_expired(files, &policy, now)
+            .into_iter()
+            .map(|f| f.path.to_string_lossy().into_owned())
+            .collect();
+
+        assert_eq!(expired, vec!["c.jpg", "old.jpg"]);
+    }
+
+    #[test]
+    fn test_zero_disables_limits() {
+        let now = SystemTime::now();
+        let files = vec![file("a.jpg", u64::MAX / 4, 365 * 24 * 60 * 60, now)];
+        let policy = RetentionPolicy { max_age_days: 0, max_files: 0, max_size_mb: 0 };
+
+        assert!(select_expired(files, &policy, now).is_empty());
+    }
+}
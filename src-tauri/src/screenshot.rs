@@ -1,8 +1,14 @@
 // screenshot.rs
 use std::error::Error;
 use std::time::{SystemTime, UNIX_EPOCH};
+use image::{DynamicImage, RgbaImage};
+use tracing::{info, warn};
 use xcap::Window;
 
+use crate::encoding::CaptureFormat;
+use crate::error::{AppError, ErrorCode};
+use crate::viewport;
+
 /// Structure to hold window information
 #[derive(Debug)]
 pub struct WindowInfo {
@@ -14,19 +20,140 @@ pub struct WindowInfo {
     pub is_maximized: bool,
 }
 
+/// How the game is captured, from the `capture_strategy` setting
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureStrategy {
+    /// Capture the window contents only
+    Window,
+    /// Capture the monitor showing the window, needed for exclusive fullscreen
+    Monitor,
+    /// Capture the window and fall back to the monitor when the frame is blank
+    Auto,
+}
+
+impl CaptureStrategy {
+    pub fn parse(value: &str) -> Option<CaptureStrategy> {
+        match value.trim().to_lowercase().as_str() {
+            "window" => Some(CaptureStrategy::Window),
+            "monitor" => Some(CaptureStrategy::Monitor),
+            "auto" => Some(CaptureStrategy::Auto),
+            _ => None,
+        }
+    }
+}
+
 /// Captures a window screenshot by partial title match and returns the image data as JPEG
 pub fn capture_window(window_titles: &[&str]) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+    capture_game(window_titles, CaptureStrategy::Window, CaptureFormat::default())
+}
+
+/// Captures the game with the given strategy and returns the image data in `format`.
+/// A black or single-colour frame is reported as a capture failure instead of being sent to OCR.
+/// Window chrome and letterbox bars are cropped away so regions line up with the game viewport.
+pub fn capture_game(window_titles: &[&str], strategy: CaptureStrategy, format: CaptureFormat) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
     let window = find_window(window_titles)?;
 
-    // Capture the window image
-    let image = window.capture_image()?;
+    let image = match strategy {
+        CaptureStrategy::Window => capture_window_image(&window)?,
+        CaptureStrategy::Monitor => capture_monitor_image(&window)?,
+        CaptureStrategy::Auto => {
+            // Minimized windows only ever return a stale or empty buffer
+            let window_image = if window.is_minimized() {
+                None
+            } else {
+                match capture_window_image(&window) {
+                    Ok(image) if !is_blank_frame(&image) => Some(image),
+                    Ok(_) => {
+                        info!("Window capture of {} is blank, capturing its monitor", window.title());
+                        None
+                    }
+                    Err(e) => {
+                        warn!("Window capture failed, capturing its monitor: {}", e);
+                        None
+                    }
+                }
+            };
+
+            match window_image {
+                Some(image) => image,
+                None => capture_monitor_image(&window)?,
+            }
+        }
+    };
+
+    if is_blank_frame(&image) {
+        return Err(AppError::new(
+            ErrorCode::CaptureFailed,
+            format!("Captured a blank frame from {}, is the game minimized or in exclusive fullscreen?", window.title()),
+        ).into());
+    }
+
+    let scale_factor = window.current_monitor().scale_factor();
+    let (width, height) = image.dimensions();
+    let (image, area) = viewport::normalize(image, scale_factor);
+    if (area.width, area.height) != (width, height) {
+        info!("Cropped {}x{} capture to game viewport {:?} at scale {}", width, height, area, scale_factor);
+    }
+
+    let data = format.encode(&DynamicImage::ImageRgba8(image)).map_err(AppError::from)?;
+    Ok(data)
+}
+
+fn capture_window_image(window: &Window) -> Result<RgbaImage, AppError> {
+    window
+        .capture_image()
+        .map_err(|e| AppError::new(ErrorCode::CaptureFailed, format!("Failed to capture window {}: {}", window.title(), e)))
+}
+
+/// Captures the monitor the window is on, cropped to the window when it is fully visible
+fn capture_monitor_image(window: &Window) -> Result<RgbaImage, AppError> {
+    let monitor = window.current_monitor();
+    let image = monitor
+        .capture_image()
+        .map_err(|e| AppError::new(ErrorCode::CaptureFailed, format!("Failed to capture monitor {}: {}", monitor.name(), e)))?;
+
+    // Window coordinates are global, move them into the monitor image
+    let x = window.x() - monitor.x();
+    let y = window.y() - monitor.y();
+    let fits = !window.is_minimized()
+        && x >= 0
+        && y >= 0
+        && window.width() > 0
+        && window.height() > 0
+        && x as u32 + window.width() <= image.width()
+        && y as u32 + window.height() <= image.height();
+
+    if fits {
+        Ok(image::imageops::crop_imm(&image, x as u32, y as u32, window.width(), window.height()).to_image())
+    } else {
+        Ok(image)
+    }
+}
+
+/// Detects black or uniform frames, as returned for exclusive-fullscreen or minimized games.
+/// Samples a grid of pixels so a full 4K frame stays cheap to check.
+pub fn is_blank_frame(image: &RgbaImage) -> bool {
+    const GRID: u32 = 64;
+    if image.width() == 0 || image.height() == 0 {
+        return true;
+    }
 
-    // Convert to JPEG with quality settings
-    let mut jpeg_data = Vec::new();
-    let mut encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut jpeg_data, 100);
-    encoder.encode_image(&image)?;
+    let mut min = u8::MAX;
+    let mut max = u8::MIN;
+    for row in 0..GRID {
+        for column in 0..GRID {
+            let x = (column * image.width() / GRID).min(image.width() - 1);
+            let y = (row * image.height() / GRID).min(image.height() - 1);
+            let [r, g, b, _] = image.get_pixel(x, y).0;
+            let luma = ((r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000) as u8;
+            min = min.min(luma);
+            max = max.max(luma);
+        }
+    }
 
-    Ok(jpeg_data)
+    // Nearly black everywhere, or a single flat colour with only compression noise
+    max < 16 || max - min < 4
 }
 
 /// Captures a window screenshot and saves it to a file
@@ -59,7 +186,7 @@ pub fn list_windows() -> Result<Vec<WindowInfo>, Box<dyn Error + Send + Sync>> {
 
 /// Helper function to find a window by trying multiple partial title matches
 fn find_window(window_titles: &[&str]) -> Result<Window, Box<dyn Error + Send + Sync>> {
-    let windows = Window::all()?;
+    let windows = Window::all().map_err(|e| AppError::new(ErrorCode::CaptureFailed, format!("Failed to list windows: {}", e)))?;
 
     for window in windows {
         let title = window.title().to_string().to_lowercase();
@@ -70,7 +197,7 @@ fn find_window(window_titles: &[&str]) -> Result<Window, Box<dyn Error + Send +
         }
     }
 
-    Err("No matching window found".into())
+    Err(AppError::new(ErrorCode::WindowNotFound, format!("No window matching {:?} found", window_titles)).into())
 }
 
 #[cfg(test)]
@@ -90,6 +217,23 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_is_blank_frame() {
+        let black = RgbaImage::from_pixel(320, 180, image::Rgba([3, 3, 3, 255]));
+        let grey = RgbaImage::from_pixel(320, 180, image::Rgba([120, 120, 120, 255]));
+        let mut summary = grey.clone();
+        for x in 40..200 {
+            for y in 20..60 {
+                summary.put_pixel(x, y, image::Rgba([240, 240, 240, 255]));
+            }
+        }
+
+        assert!(is_blank_frame(&black));
+        assert!(is_blank_frame(&grey));
+        assert!(!is_blank_frame(&summary));
+        assert_eq!(CaptureStrategy::parse(" Monitor "), Some(CaptureStrategy::Monitor));
+    }
+
     #[test]
     fn test_list_windows() {
         let windows = list_windows().unwrap();
@@ -1,87 +1,141 @@
 use crate::crop;
-use crate::ocr;
+use crate::debug::DebugJob;
+use crate::encoding::{self, CaptureFormat};
+use crate::error::{AppError, AppResult};
+use crate::labels::{self, Label};
+use crate::localize::{self, RegionLayout};
+use crate::pipeline;
+use crate::privacy;
 use crate::screenshot;
-use crate::AppState;
 use base64::engine::general_purpose::STANDARD;
 use base64::Engine;
 use std::error::Error;
 use tauri::{AppHandle, Manager, Emitter, path::BaseDirectory};
 use tauri_plugin_global_shortcut::{Code, GlobalShortcutExt, Modifiers, Shortcut, ShortcutState};
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use lazy_static::lazy_static;
 use std::time::{SystemTime, UNIX_EPOCH};
-use image::ImageError;
-use crate::models::settings::settings::dsl::*;
+use image::DynamicImage;
+use std::sync::Arc;
+use tracing::Instrument;
+use tracing::{debug, error, info, warn};
 use diesel::prelude::*;
 
+/// Used when the `shortcut` setting is missing or the database is unavailable
+const DEFAULT_SHORTCUT: &str = "Ctrl+Shift+M";
+
 lazy_static! {
     pub static ref IS_PROCESSING: AtomicBool = AtomicBool::new(false);
+    static ref CAPTURE_JOB: AtomicU64 = AtomicU64::new(0);
 }
 
-async fn capture_screenshot(app_handle: &AppHandle) -> Result<Option<String>, Box<dyn Error + Send + Sync>> {
+async fn capture_screenshot(app_handle: &AppHandle) -> AppResult<(String, CaptureFormat, Arc<RegionLayout>)> {
     let start_time = std::time::Instant::now();
-    match screenshot::capture_window(&[".jpg", "notepad", "hunt", "Hunt: Showdown"]) {
-        Ok(image_data) => {
-            let screenshot_time = start_time.elapsed();
-            println!("Screenshot captured in {:?}, size: {} bytes", screenshot_time, image_data.len());
-
-            let base64_image = STANDARD.encode(&image_data);
-            let estimated_size_mb = base64_image.len() as f64 / (1024.0 * 1024.0);
-            println!("Estimated image size: {:.2} MB", estimated_size_mb);
-
-            // Save the screenshot as JPEG
-            let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
-            let debug_path = app_handle.path().resolve("debug_images", BaseDirectory::AppData)?;
-            std::fs::create_dir_all(&debug_path).map_err(|e| ImageError::IoError(e))?;
-            let filename = format!("{}/screenshot_{}.jpg", debug_path.to_str().unwrap(), timestamp);
-            std::fs::write(&filename, &image_data)?;
-
-            Ok(Some(base64_image))
-        }
-        Err(e) => {
-            println!("Error capturing screenshot: {:?}", e);
-            Ok(None)
-        }
+    let format = encoding::configured(app_handle).await;
+    let strategy = crate::db::setting(app_handle, "capture_strategy")
+        .await
+        .and_then(|v| screenshot::CaptureStrategy::parse(&v))
+        .unwrap_or(screenshot::CaptureStrategy::Auto);
+    let image_data = screenshot::capture_game(&[".jpg", "notepad", "hunt", "Hunt: Showdown"], strategy, format)?;
+    // Localize once, the layout places the privacy areas and every region cropped later
+    let image = Arc::new(encoding::decode(&image_data, Some(format))?);
+    let layout = Arc::new(localize::locate(app_handle, image.clone()).await);
+    // Names are hidden before the capture is written anywhere
+    let privacy = privacy::Privacy::configured(app_handle).await;
+    let image_data = privacy::redact_capture(&privacy, &image, &layout, image_data, format)?;
+    let screenshot_time = start_time.elapsed();
+    info!("Screenshot captured in {:?} as {}, size: {} bytes", screenshot_time, format.name(), image_data.len());
+
+    let base64_image = STANDARD.encode(&image_data);
+    let estimated_size_mb = base64_image.len() as f64 / (1024.0 * 1024.0);
+    debug!("Estimated image size: {:.2} MB", estimated_size_mb);
+
+    // Keep a copy of the capture, a failure here only loses the debug copy
+    if let Err(e) = save_capture(app_handle, &image_data, format) {
+        warn!("Error saving capture to debug_images: {}", e);
     }
+
+    Ok((base64_image, format, layout))
+}
+
+fn save_capture(app_handle: &AppHandle, image_data: &[u8], format: CaptureFormat) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let debug_path = app_handle.path().resolve("debug_images", BaseDirectory::AppData)?;
+    std::fs::create_dir_all(&debug_path)?;
+    std::fs::write(debug_path.join(format!("screenshot_{}.{}", timestamp, format.extension())), image_data)?;
+    Ok(())
+}
+
+/// Reports a failed capture job to the frontend
+fn report_error(app_handle: &AppHandle, error: &AppError) {
+    error!(code = ?error.code, "Capture job failed: {}", error.message);
+    let _ = app_handle.emit("screenshot-status", "error");
+    let _ = app_handle.emit("screenshot-error", error);
 }
 
 async fn crop_image(app_handle: &AppHandle, base64_image: &str, region: crop::CropRegion) -> Result<String, Box<dyn Error + Send + Sync>> {
     let _ = app_handle.emit("screenshot-status", "cropping");
-    println!("Base64 image length: {}", base64_image.len());
-    println!("Base64 image (first 100 chars): {}", &base64_image[..100.min(base64_image.len())]);
+    debug!("Base64 image length: {}", base64_image.len());
+    debug!("Base64 image (first 100 chars): {}", &base64_image[..100.min(base64_image.len())]);
     let crop_start = std::time::Instant::now();
     match crop::crop_image(app_handle.clone(), base64_image.to_string(), region).await {
         Ok(cropped_image) => {
             let crop_time = crop_start.elapsed();
-            println!("Image cropped in {:?}", crop_time);
+            info!("Image cropped in {:?}", crop_time);
             Ok(cropped_image)
         }
         Err(e) => {
-            println!("Error cropping image: {:?}", e);
+            error!("Error cropping image: {:?}", e);
             Err(e.into())
         }
     }
 }
 
-async fn perform_ocr(app_handle: &AppHandle, base64_image: &str) -> Result<Option<i32>, Box<dyn Error + Send + Sync>> {
+async fn recognize_region(app_handle: &AppHandle, image: &Arc<DynamicImage>, layout: &Arc<RegionLayout>, region: crop::CropRegion, job: &mut Option<DebugJob>) -> Result<Vec<String>, Box<dyn Error + Send + Sync>> {
+    let outcome = pipeline::process_decoded_regions(app_handle, image.clone(), layout.clone(), &[region])
+        .await?
+        .pop()
+        .ok_or("No OCR result for region")?;
+
+    // Keep the crop and word boxes when debug artifacts are enabled
+    if let Some(job) = job.as_mut() {
+        job.record_region(region, outcome.rect, &outcome.crop, &outcome.result, outcome.crop_time, outcome.ocr_time);
+    }
+
+    Ok(outcome.result.line_texts())
+}
+
+async fn perform_ocr(app_handle: &AppHandle, base64_image: &str, format: CaptureFormat, layout: Arc<RegionLayout>) -> Result<Option<i32>, Box<dyn Error + Send + Sync>> {
+    let mut job = DebugJob::start(app_handle, base64_image).await;
+    let image = Arc::new(crop::decode_image_as(base64_image, Some(format))?);
+    let result = detect_mission_summary(app_handle, base64_image, format, &image, &layout, &mut job).await;
+
+    if let Some(job) = job {
+        let job_id = job.id().to_string();
+        job.finish();
+        info!("Debug artifacts written for job {}", job_id);
+    }
+
+    result
+}
+
+async fn detect_mission_summary(app_handle: &AppHandle, base64_image: &str, format: CaptureFormat, image: &Arc<DynamicImage>, layout: &Arc<RegionLayout>, job: &mut Option<DebugJob>) -> Result<Option<i32>, Box<dyn Error + Send + Sync>> {
     let _ = app_handle.emit("screenshot-status", "recognizing");
     
     // First, check if it's a mission summary screen
-    let mission_summary_crop = crop_image(app_handle, base64_image, crop::CropRegion::MissionSummary).await?;
-    let mission_summary_text = ocr::perform_ocr(app_handle.clone(), mission_summary_crop).await?;
+    let mission_summary_text = recognize_region(app_handle, image, layout, crop::CropRegion::MissionSummary, job).await?;
     
-    let has_mission_summary = mission_summary_text.iter()
-        .any(|line| line.to_lowercase().contains("mission summary"));
+    // The heading also tells us which language the game client runs in
+    let game_language = labels::match_mission_summary(app_handle, &mission_summary_text.join(" ")).await;
     
-    if has_mission_summary {
+    if let Some(game_language) = game_language {
+        info!("Mission summary heading found in {:?}", game_language);
+
         // If it is a mission summary, check the first summary region for mission type
-        let summary_first_crop = crop_image(app_handle, base64_image, crop::CropRegion::SummaryFirst).await?;
-        let summary_first_text = ocr::perform_ocr(app_handle.clone(), summary_first_crop).await?;
+        let summary_first_text = recognize_region(app_handle, image, layout, crop::CropRegion::SummaryFirst, job).await?.join(" ");
         
-        let has_bounty_mission = summary_first_text.iter()
-            .any(|line| line.to_lowercase().contains("bounty collected"));
-        let has_soul_survival = summary_first_text.iter()
-            .any(|line| line.to_lowercase().contains("rifts closed"));
+        let has_bounty_mission = game_language.contains(Label::BountyCollected, &summary_first_text);
+        let has_soul_survival = game_language.contains(Label::RiftsClosed, &summary_first_text);
         
         // Determine mission type
         let mission_type = if has_bounty_mission {
@@ -92,59 +146,41 @@ async fn perform_ocr(app_handle: &AppHandle, base64_image: &str) -> Result<Optio
             "unknown"
         };
         
-        println!("Detected mission type: {}", mission_type);
+        info!("Detected mission type: {}", mission_type);
         
         // Update database if a valid mission type is detected
         if mission_type != "unknown" {
-            if let Some(db) = app_handle.state::<AppState>().inner().db.as_ref() {
-                if let Ok(mut conn) = db.lock() {
-                    match crate::db::save_screenshot(&mut conn, base64_image.to_string(), mission_type.to_string()) {
-                        Ok(screenshot_id) => {
-                            println!("Screenshot saved to database with id: {}", screenshot_id);
-                            
-                            use diesel::prelude::*;
-                            use crate::models::screenshots::dsl::*;
-                            
-                            // Update the recognized field for the specific screenshot
-                            diesel::update(screenshots.filter(id.eq(screenshot_id)))
-                                .set(recognized.eq(true))
-                                .execute(&mut *conn)
-                                .unwrap_or_else(|e| {
-                                    println!("Error updating screenshot recognized status: {:?}", e);
-                                    0
-                                });
-
-                            let _ = app_handle.emit("screenshot-status", "detected");
-                            let _ = app_handle.emit("open-screenshot-viewer", ());
-                            println!("Mission Summary detected");
-                            return Ok(Some(screenshot_id));
-                        }
-                        Err(e) => {
-                            println!("Error saving screenshot: {:?}", e);
-                            return Ok(None);
-                        }
-                    }
-                }
-            }
+            let image_data = base64_image.to_string();
+            let screenshot_id = crate::db::run(app_handle, move |conn| {
+                use crate::models::screenshots::dsl::*;
+
+                let screenshot_id = crate::db::save_screenshot(conn, image_data, format, mission_type.to_string(), Some(game_language))?;
+
+                // Update the recognized field for the specific screenshot
+                diesel::update(screenshots.filter(id.eq(screenshot_id)))
+                    .set(recognized.eq(true))
+                    .execute(conn)?;
+                Ok::<_, diesel::result::Error>(screenshot_id)
+            })
+            .await?;
+            info!("Screenshot saved to database with id: {}", screenshot_id);
+
+            let _ = app_handle.emit("screenshot-status", "detected");
+            let _ = app_handle.emit("open-screenshot-viewer", ());
+            info!("Mission Summary detected");
+            return Ok(Some(screenshot_id));
         }
     }
     
     let _ = app_handle.emit("screenshot-status", "not-detected");
-    println!("No valid mission summary detected");
+    info!("No valid mission summary detected");
     Ok(None)
 }
 
-pub fn get_shortcut(app_handle: &AppHandle) -> Result<String, Box<dyn Error + Send + Sync>> {
-    if let Some(db) = app_handle.state::<AppState>().inner().db.as_ref() {
-        if let Ok(mut conn) = db.lock() {
-            let shortcut_value: String = settings
-                .filter(key.eq("shortcut"))
-                .select(value)
-                .first(&mut *conn)?;
-            return Ok(shortcut_value);
-        }
-    }
-    Ok("Ctrl+Shift+M".to_string()) // Default fallback if database query fails
+pub async fn get_shortcut(app_handle: &AppHandle) -> String {
+    crate::db::setting(app_handle, "shortcut")
+        .await
+        .unwrap_or_else(|| DEFAULT_SHORTCUT.to_string())
 }
 
 pub fn format_key_for_code(input_key: &str) -> String {
@@ -166,32 +202,36 @@ pub fn setup_shortcut_handler(app_handle: &AppHandle) -> Result<(), Box<dyn Erro
                 if event.state() == ShortcutState::Pressed {
                     // Check if we're already processing a screenshot
                     if IS_PROCESSING.load(Ordering::SeqCst) {
-                        println!("Screenshot processing in progress, please wait...");
+                        info!("Screenshot processing in progress, please wait...");
                         return;
                     }
 
                     // Set the processing flag
                     IS_PROCESSING.store(true, Ordering::SeqCst);
-                    println!("Taking screenshot...");
+                    info!("Taking screenshot...");
 
                     let _ = app_handle_clone.emit("screenshot-status", "capturing");
 
+                    // Every log line of this capture carries the job number
+                    let job = CAPTURE_JOB.fetch_add(1, Ordering::SeqCst) + 1;
+                    let span = tracing::info_span!("capture", job);
+
                     let handle = app_handle_clone.clone();
                     tauri::async_runtime::spawn(async move {
-                        let _result = async {
-                            if let Ok(Some(base64_image)) = capture_screenshot(&handle).await {
-                                match crop_image(&handle, &base64_image, crop::CropRegion::MissionSummary).await {
-                                    Ok(_) => {
-                                        let _ = perform_ocr(&handle, &base64_image).await;
-                                    }
-                                    Err(e) => println!("Error in cropping: {:?}", e),
-                                }
-                            }
+                        let result = async {
+                            let (base64_image, format, layout) = capture_screenshot(&handle).await?;
+                            crop_image(&handle, &base64_image, crop::CropRegion::MissionSummary).await?;
+                            perform_ocr(&handle, &base64_image, format, layout).await?;
+                            Ok::<_, AppError>(())
                         }.await;
 
+                        if let Err(e) = result {
+                            report_error(&handle, &e);
+                        }
+
                         // Reset the processing flag when we're done
                         IS_PROCESSING.store(false, Ordering::SeqCst);
-                    });
+                    }.instrument(span));
                 }
             })
             .build(),
@@ -209,7 +249,8 @@ pub fn register_shortcut(app_handle: &AppHandle, shortcut: Shortcut) -> Result<(
 pub fn register_shortcuts(app_handle: &AppHandle) -> Result<(), Box<dyn Error + Send + Sync>> {
     #[cfg(desktop)]
     {
-        let shortcut_str = get_shortcut(app_handle)?;
+        // Runs during setup, before the async runtime handles any work
+        let shortcut_str = crate::db::read_setting(app_handle, "shortcut").unwrap_or_else(|| DEFAULT_SHORTCUT.to_string());
         let parts: Vec<&str> = shortcut_str.split('+').collect();
         
         let mut modifiers = Modifiers::empty();
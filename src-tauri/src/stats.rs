@@ -0,0 +1,111 @@
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use diesel::prelude::*;
+use std::collections::HashMap;
+
+use crate::db::DbConnection;
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::models::{match_fields, screenshots, MatchFieldRecord};
+
+/// Totals over the recognized matches
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct MatchStats {
+    pub matches: i64,
+    /// Number of matches per mission type
+    pub mission_types: HashMap<String, i64>,
+    /// Sum of every numeric card, keyed by label such as `hunters_killed`
+    pub totals: HashMap<String, i64>,
+}
+
+/// Parses a `since` filter given as `YYYY-MM-DD` or `YYYY-MM-DDTHH:MM:SS`, blank means no filter
+pub fn parse_since(value: Option<&str>) -> AppResult<Option<NaiveDateTime>> {
+    match value.map(str::trim).filter(|s| !s.is_empty()) {
+        Some(value) => NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S")
+            .or_else(|_| NaiveDate::parse_from_str(value, "%Y-%m-%d").map(|date| date.and_time(NaiveTime::MIN)))
+            .map(Some)
+            .map_err(|e| AppError::new(ErrorCode::InvalidInput, format!("Invalid date {}: {}", value, e))),
+        None => Ok(None),
+    }
+}
+
+/// Aggregates recognized matches, optionally only those captured after `since`.
+/// Manually corrected values, labels and mission types take precedence over the OCR output.
+pub fn collect(conn: &mut DbConnection, since: Option<NaiveDateTime>) -> Result<MatchStats, diesel::result::Error> {
+    let mut query = screenshots::table
+        .filter(screenshots::recognized.eq(true))
+        .select((screenshots::id, screenshots::mission_type, screenshots::manual_mission_type))
+        .into_boxed();
+    if let Some(since) = since {
+        query = query.filter(screenshots::created_at.ge(since));
+    }
+    let matches: Vec<(i32, String)> = query
+        .load::<(i32, String, Option<String>)>(conn)?
+        .into_iter()
+        .map(|(id, detected, manual)| (id, manual.unwrap_or(detected)))
+        .collect();
+
+    let ids: Vec<i32> = matches.iter().map(|(id, _)| *id).collect();
+    let fields: Vec<MatchFieldRecord> = match_fields::table
+        .filter(match_fields::screenshot_id.eq_any(ids))
+        .select(MatchFieldRecord::as_select())
+        .load(conn)?;
+
+    Ok(aggregate(&matches, &fields))
+}
+
+fn aggregate(matches: &[(i32, String)], fields: &[MatchFieldRecord]) -> MatchStats {
+    let mut stats = MatchStats {
+        matches: matches.len() as i64,
+        ..Default::default()
+    };
+
+    for (_, mission_type) in matches {
+        *stats.mission_types.entry(mission_type.clone()).or_insert(0) += 1;
+    }
+
+    for field in fields {
+        if let (Some(label), Some(number)) = (field.effective_label(), field.effective_number()) {
+            *stats.totals.entry(label.to_string()).or_insert(0) += number;
+        }
+    }
+
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(label: &str, number: Option<i64>, manual_number: Option<i64>) -> MatchFieldRecord {
+        MatchFieldRecord {
+            id: 1,
+            screenshot_id: 1,
+            region: "SummaryFirst".to_string(),
+            label: Some(label.to_string()),
+            raw_text: String::new(),
+            value: number.map(|n| n.to_string()).unwrap_or_default(),
+            number,
+            corrected: false,
+            suspicious: false,
+            manual_value: manual_number.map(|n| n.to_string()),
+            manual_number,
+            manual_label: None,
+        }
+    }
+
+    #[test]
+    fn test_aggregate_prefers_manual_values() {
+        let matches = vec![(1, "bounty".to_string()), (2, "bounty".to_string()), (3, "soul_survival".to_string())];
+        let fields = vec![
+            field("hunters_killed", Some(3), None),
+            field("hunters_killed", Some(8), Some(2)),
+            field("monsters_killed", None, None),
+        ];
+
+        let stats = aggregate(&matches, &fields);
+
+        assert_eq!(stats.matches, 3);
+        assert_eq!(stats.mission_types["bounty"], 2);
+        assert_eq!(stats.totals["hunters_killed"], 5);
+        assert!(!stats.totals.contains_key("monsters_killed"));
+    }
+}
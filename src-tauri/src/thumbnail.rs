@@ -0,0 +1,155 @@
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use diesel::prelude::*;
+use image::ImageError;
+use std::error::Error;
+use tauri::AppHandle;
+use tracing::{error, info, warn};
+
+use crate::crop::{self, CropRegion};
+use crate::encoding::CaptureFormat;
+use crate::error::{AppError, ErrorCode};
+use crate::models::{region_previews, screenshots};
+
+/// Largest thumbnail size, the aspect ratio of the capture is kept
+const THUMBNAIL_WIDTH: u32 = 320;
+const THUMBNAIL_HEIGHT: u32 = 180;
+
+/// Previews are for browsing only, a lossy encoding keeps them a few kilobytes each
+const PREVIEW_FORMAT: CaptureFormat = CaptureFormat::Jpeg { quality: 80 };
+
+/// Screenshots rendered per backfill batch
+const BACKFILL_BATCH: i64 = 10;
+
+/// Base64 JPEG thumbnail of a capture and the unprocessed crop of every region
+#[derive(Debug, Clone)]
+pub struct Previews {
+    pub thumbnail: String,
+    pub regions: Vec<(CropRegion, String)>,
+}
+
+/// Decodes a stored capture and renders its thumbnail and region previews
+pub fn render(base64_image: &str, format: Option<CaptureFormat>) -> Result<Previews, ImageError> {
+    let img = crop::decode_image_as(base64_image, format)?;
+
+    let thumbnail = BASE64.encode(PREVIEW_FORMAT.encode(&img.thumbnail(THUMBNAIL_WIDTH, THUMBNAIL_HEIGHT))?);
+    let regions = CropRegion::ALL
+        .into_iter()
+        .map(|region| Ok((region, BASE64.encode(PREVIEW_FORMAT.encode(&crop::crop_region(&img, region))?))))
+        .collect::<Result<Vec<_>, ImageError>>()?;
+
+    Ok(Previews { thumbnail, regions })
+}
+
+/// The thumbnail of a screenshot, or the preview of one of its regions, as base64 JPEG.
+/// Previews missing because the backfill hasn't reached the screenshot yet are rendered on demand.
+pub async fn get(app: &AppHandle, screenshot_id: i32, region: Option<CropRegion>) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let stored = crate::db::run(app, move |conn| match region {
+        Some(region) => region_previews::table
+            .filter(region_previews::screenshot_id.eq(screenshot_id))
+            .filter(region_previews::region.eq(format!("{:?}", region)))
+            .select(region_previews::image)
+            .first::<String>(conn)
+            .optional(),
+        None => screenshots::table
+            .filter(screenshots::id.eq(screenshot_id))
+            .select(screenshots::thumbnail)
+            .first::<Option<String>>(conn)
+            .map(Option::flatten),
+    })
+    .await?;
+    if let Some(image) = stored {
+        return Ok(image);
+    }
+
+    let previews = backfill_one(app, screenshot_id).await?;
+    match region {
+        Some(region) => previews
+            .regions
+            .into_iter()
+            .find(|(preview_region, _)| *preview_region == region)
+            .map(|(_, image)| image)
+            .ok_or_else(|| AppError::new(ErrorCode::NotFound, format!("No preview for {:?}", region)).into()),
+        None => Ok(previews.thumbnail),
+    }
+}
+
+/// Starts the background task that renders previews for screenshots saved before they existed
+pub fn start_backfill(app: &AppHandle) {
+    let handle = app.clone();
+    tauri::async_runtime::spawn(async move {
+        match backfill(&handle).await {
+            Ok(0) => {}
+            Ok(rendered) => info!("Rendered previews for {} stored screenshots", rendered),
+            Err(e) => error!("Thumbnail backfill failed: {}", e),
+        }
+    });
+}
+
+async fn backfill(app: &AppHandle) -> Result<usize, Box<dyn Error + Send + Sync>> {
+    let mut rendered = 0;
+    // Screenshots that fail to render are skipped so the same rows aren't picked again
+    let mut failed: Vec<i32> = Vec::new();
+
+    loop {
+        let skip = failed.clone();
+        let ids: Vec<i32> = crate::db::run(app, move |conn| {
+            screenshots::table
+                .filter(screenshots::thumbnail.is_null())
+                .filter(screenshots::id.ne_all(skip))
+                .select(screenshots::id)
+                .order(screenshots::id.desc())
+                .limit(BACKFILL_BATCH)
+                .load(conn)
+        })
+        .await?;
+        if ids.is_empty() {
+            return Ok(rendered);
+        }
+
+        for screenshot_id in ids {
+            match backfill_one(app, screenshot_id).await {
+                Ok(_) => rendered += 1,
+                Err(e) => {
+                    warn!("Failed to render previews for screenshot {}: {}", screenshot_id, e);
+                    failed.push(screenshot_id);
+                }
+            }
+        }
+    }
+}
+
+/// Renders and stores the previews of one screenshot, decoding off the connection pool
+async fn backfill_one(app: &AppHandle, screenshot_id: i32) -> Result<Previews, Box<dyn Error + Send + Sync>> {
+    let (image_data, image_format): (String, Option<String>) = crate::db::run(app, move |conn| {
+        screenshots::table
+            .filter(screenshots::id.eq(screenshot_id))
+            .select((screenshots::image, screenshots::image_format))
+            .first(conn)
+    })
+    .await?;
+
+    let format = image_format.as_deref().and_then(CaptureFormat::parse);
+    let previews = tokio::task::spawn_blocking(move || render(&image_data, format)).await??;
+
+    let to_save = previews.clone();
+    crate::db::run(app, move |conn| crate::db::save_previews(conn, screenshot_id, &to_save)).await?;
+
+    Ok(previews)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_previews() {
+        let img = image::DynamicImage::ImageRgb8(image::RgbImage::from_pixel(1920, 1080, image::Rgb([40, 40, 40])));
+        let base64_image = BASE64.encode(CaptureFormat::Png.encode(&img).unwrap());
+
+        let previews = render(&base64_image, Some(CaptureFormat::Png)).unwrap();
+        let thumbnail = image::load_from_memory(&BASE64.decode(&previews.thumbnail).unwrap()).unwrap();
+
+        assert_eq!((thumbnail.width(), thumbnail.height()), (THUMBNAIL_WIDTH, THUMBNAIL_HEIGHT));
+        assert_eq!(previews.regions.len(), CropRegion::ALL.len());
+    }
+}
@@ -0,0 +1,257 @@
+use image::RgbaImage;
+
+use crate::crop::{BASE_HEIGHT, BASE_WIDTH};
+
+/// Pixels sampled along each row or column
+const SAMPLES: u32 = 64;
+/// Tallest title bar at 100% scaling, including its top border
+const MAX_TITLE_BAR: f32 = 40.0;
+/// Widest side or bottom border at 100% scaling
+const MAX_BORDER: f32 = 10.0;
+/// Relative aspect ratio difference still treated as the game's aspect ratio
+const ASPECT_TOLERANCE: f32 = 0.01;
+/// Brightest luma of a letterbox bar
+const BLACK_LUMA: u8 = 16;
+
+/// The area of a capture the game actually renders to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub struct Viewport {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Viewport {
+    fn full(width: u32, height: u32) -> Self {
+        Viewport { x: 0, y: 0, width, height }
+    }
+
+    fn aspect_matches(&self) -> bool {
+        aspect_matches(self.width, self.height)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Edge {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+/// Crops a capture to the game viewport, returns the image unchanged when it already is one
+pub fn normalize(image: RgbaImage, scale_factor: f32) -> (RgbaImage, Viewport) {
+    let viewport = find_viewport(&image, scale_factor);
+    if viewport == Viewport::full(image.width(), image.height()) {
+        return (image, viewport);
+    }
+
+    let cropped = image::imageops::crop_imm(&image, viewport.x, viewport.y, viewport.width, viewport.height).to_image();
+    (cropped, viewport)
+}
+
+/// Finds the game viewport inside a capture.
+///
+/// Window chrome is removed first: a title bar and borders show up as flat rows and columns at
+/// the edges, no thicker than their default size times `scale_factor`. Black letterbox or
+/// pillarbox bars are removed next. When bars can't be measured, e.g. because the game itself
+/// is dark at the edges, the viewport is the centered area with the game's aspect ratio.
+/// Captures that already have the game's aspect ratio are returned whole, so this is safe to
+/// apply to fullscreen captures.
+pub fn find_viewport(image: &RgbaImage, scale_factor: f32) -> Viewport {
+    let full = Viewport::full(image.width(), image.height());
+    if full.width == 0 || full.height == 0 || full.aspect_matches() {
+        return full;
+    }
+
+    let scale = if scale_factor.is_finite() && scale_factor > 0.0 { scale_factor } else { 1.0 };
+    let max_title_bar = (MAX_TITLE_BAR * scale).round() as u32;
+    let max_border = (MAX_BORDER * scale).round() as u32;
+
+    let is_chrome = |samples: &[[u8; 3]]| is_flat(samples) && !is_black(samples);
+    let top = count_edge(image, full, Edge::Top, max_title_bar, is_chrome);
+    let content = Viewport { y: top, height: full.height - top, ..full };
+    let bottom = count_edge(image, content, Edge::Bottom, max_border, is_chrome);
+    let content = Viewport { height: content.height - bottom, ..content };
+    let left = count_edge(image, content, Edge::Left, max_border, is_chrome);
+    let content = Viewport { x: left, width: content.width - left, ..content };
+    let right = count_edge(image, content, Edge::Right, max_border, is_chrome);
+    let content = Viewport { width: content.width - right, ..content };
+
+    if content.aspect_matches() {
+        return content;
+    }
+
+    let letterboxed = remove_bars(image, content);
+    if letterboxed.aspect_matches() {
+        return letterboxed;
+    }
+
+    fit_aspect(content)
+}
+
+/// Removes black bars on the sides that are too wide or too tall for the game's aspect ratio
+fn remove_bars(image: &RgbaImage, area: Viewport) -> Viewport {
+    let wide = area.width as u64 * BASE_HEIGHT as u64 > area.height as u64 * BASE_WIDTH as u64;
+
+    if wide {
+        let limit = area.width / 2;
+        let left = count_edge(image, area, Edge::Left, limit, is_black);
+        let area = Viewport { x: area.x + left, width: area.width - left, ..area };
+        let right = count_edge(image, area, Edge::Right, area.width / 2, is_black);
+        Viewport { width: area.width - right, ..area }
+    } else {
+        let limit = area.height / 2;
+        let top = count_edge(image, area, Edge::Top, limit, is_black);
+        let area = Viewport { y: area.y + top, height: area.height - top, ..area };
+        let bottom = count_edge(image, area, Edge::Bottom, area.height / 2, is_black);
+        Viewport { height: area.height - bottom, ..area }
+    }
+}
+
+/// The largest centered area with the game's aspect ratio
+fn fit_aspect(area: Viewport) -> Viewport {
+    let width_for_height = (area.height as u64 * BASE_WIDTH as u64 / BASE_HEIGHT as u64) as u32;
+    if width_for_height <= area.width {
+        let x = area.x + (area.width - width_for_height) / 2;
+        Viewport { x, width: width_for_height, ..area }
+    } else {
+        let height = (area.width as u64 * BASE_HEIGHT as u64 / BASE_WIDTH as u64) as u32;
+        let y = area.y + (area.height - height) / 2;
+        Viewport { y, height, ..area }
+    }
+}
+
+fn aspect_matches(width: u32, height: u32) -> bool {
+    if width == 0 || height == 0 {
+        return false;
+    }
+    let aspect = width as f32 / height as f32;
+    let target = BASE_WIDTH as f32 / BASE_HEIGHT as f32;
+    ((aspect - target) / target).abs() <= ASPECT_TOLERANCE
+}
+
+/// Counts the rows or columns from an edge of `area` that match, up to `limit`
+fn count_edge(image: &RgbaImage, area: Viewport, edge: Edge, limit: u32, matches: impl Fn(&[[u8; 3]]) -> bool) -> u32 {
+    let lines = match edge {
+        Edge::Top | Edge::Bottom => area.height,
+        Edge::Left | Edge::Right => area.width,
+    };
+
+    let mut count = 0;
+    while count < limit.min(lines.saturating_sub(1)) {
+        let samples = match edge {
+            Edge::Top => sample_row(image, area, area.y + count),
+            Edge::Bottom => sample_row(image, area, area.y + area.height - 1 - count),
+            Edge::Left => sample_column(image, area, area.x + count),
+            Edge::Right => sample_column(image, area, area.x + area.width - 1 - count),
+        };
+        if !matches(&samples) {
+            break;
+        }
+        count += 1;
+    }
+    count
+}
+
+fn sample_row(image: &RgbaImage, area: Viewport, y: u32) -> Vec<[u8; 3]> {
+    (0..SAMPLES)
+        .map(|i| {
+            let x = area.x + (i * area.width / SAMPLES).min(area.width - 1);
+            let [r, g, b, _] = image.get_pixel(x, y).0;
+            [r, g, b]
+        })
+        .collect()
+}
+
+fn sample_column(image: &RgbaImage, area: Viewport, x: u32) -> Vec<[u8; 3]> {
+    (0..SAMPLES)
+        .map(|i| {
+            let y = area.y + (i * area.height / SAMPLES).min(area.height - 1);
+            let [r, g, b, _] = image.get_pixel(x, y).0;
+            [r, g, b]
+        })
+        .collect()
+}
+
+fn luma([r, g, b]: [u8; 3]) -> u8 {
+    ((r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000) as u8
+}
+
+fn is_black(samples: &[[u8; 3]]) -> bool {
+    samples.iter().all(|&pixel| luma(pixel) < BLACK_LUMA)
+}
+
+/// Whether most of a line is one colour, title text and buttons cover only a small part of a title bar
+fn is_flat(samples: &[[u8; 3]]) -> bool {
+    let mut lumas: Vec<u8> = samples.iter().map(|&pixel| luma(pixel)).collect();
+    lumas.sort_unstable();
+    let median = lumas[lumas.len() / 2];
+    let close = lumas.iter().filter(|&&value| value.abs_diff(median) <= 8).count();
+    close * 4 >= lumas.len() * 3
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    /// A 1920x1080 frame with some detail in every row and column
+    fn game_frame(width: u32, height: u32) -> RgbaImage {
+        RgbaImage::from_fn(width, height, |x, y| {
+            let value = ((x / 7 + y / 5) % 4 * 50 + 40) as u8;
+            Rgba([value, value / 2, 255 - value, 255])
+        })
+    }
+
+    fn embed(frame: &RgbaImage, width: u32, height: u32, x: u32, y: u32, background: Rgba<u8>) -> RgbaImage {
+        let mut canvas = RgbaImage::from_pixel(width, height, background);
+        image::imageops::replace(&mut canvas, frame, x as i64, y as i64);
+        canvas
+    }
+
+    #[test]
+    fn test_fullscreen_capture_is_unchanged() {
+        let frame = game_frame(1920, 1080);
+        assert_eq!(find_viewport(&frame, 1.0), Viewport::full(1920, 1080));
+    }
+
+    #[test]
+    fn test_removes_window_chrome_at_dpi_scale() {
+        // 150% scaling: 45px title bar and 3px borders around a 1600x900 client area
+        let mut capture = embed(&game_frame(1600, 900), 1606, 948, 3, 45, Rgba([32, 32, 32, 255]));
+        for x in 10..60 {
+            for y in 15..30 {
+                capture.put_pixel(x, y, Rgba([230, 230, 230, 255]));
+            }
+        }
+
+        assert_eq!(find_viewport(&capture, 1.5), Viewport { x: 3, y: 45, width: 1600, height: 900 });
+    }
+
+    #[test]
+    fn test_removes_letterbox_bars() {
+        let capture = embed(&game_frame(1920, 1080), 1920, 1200, 0, 60, Rgba([0, 0, 0, 255]));
+        assert_eq!(find_viewport(&capture, 1.0), Viewport { x: 0, y: 60, width: 1920, height: 1080 });
+
+        let capture = embed(&game_frame(1920, 1080), 2560, 1080, 320, 0, Rgba([0, 0, 0, 255]));
+        let (normalized, viewport) = normalize(capture, 1.0);
+        assert_eq!(viewport, Viewport { x: 320, y: 0, width: 1920, height: 1080 });
+        assert_eq!(normalized.dimensions(), (1920, 1080));
+    }
+
+    #[test]
+    fn test_falls_back_to_centered_aspect() {
+        // A dark game edge next to the bars can't be told apart, keep the centered 16:9 area
+        let mut frame = game_frame(1920, 1080);
+        for x in 0..1920 {
+            for y in 0..40 {
+                frame.put_pixel(x, y, Rgba([0, 0, 0, 255]));
+            }
+        }
+        let capture = embed(&frame, 1920, 1200, 0, 60, Rgba([0, 0, 0, 255]));
+
+        assert_eq!(find_viewport(&capture, 1.0), Viewport { x: 0, y: 60, width: 1920, height: 1080 });
+    }
+}
@@ -0,0 +1,494 @@
+use chrono::{Local, NaiveDateTime, TimeZone};
+use diesel::prelude::*;
+use lazy_static::lazy_static;
+use reqwest::multipart::{Form, Part};
+use reqwest::{Client, StatusCode, Url};
+use std::error::Error;
+use std::fmt;
+use std::time::Duration;
+use tauri::{AppHandle, Listener, Runtime};
+use tokio::sync::Notify;
+use tracing::{error, info, warn};
+
+use crate::crop::CropRegion;
+use crate::db;
+use crate::models::{screenshots, webhook_outbox, WebhookDelivery};
+use crate::thumbnail;
+
+/// Deliveries are given up on after this many failed attempts
+const MAX_ATTEMPTS: i32 = 8;
+
+/// Delay before the first retry, doubled after every further failure up to `MAX_BACKOFF_SECS`
+const BASE_BACKOFF_SECS: i64 = 30;
+const MAX_BACKOFF_SECS: i64 = 60 * 60;
+
+/// How often the outbox is checked for due retries when no new match wakes it up
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Emitted with the screenshot id after a submitted match has been recognized
+pub const SUBMITTED_EVENT: &str = "match-submitted";
+
+/// Name shown as the sender of Discord messages
+const DISCORD_USERNAME: &str = "Martini";
+
+lazy_static! {
+    /// Wakes the delivery task when a new match is queued
+    static ref WAKE: Notify = Notify::new();
+}
+
+/// JSON posted to generic webhooks, Discord webhooks get it reformatted as an embed
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct MatchPayload {
+    pub event: String,
+    pub screenshot_id: i32,
+    pub mission_type: String,
+    pub language: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub fields: Vec<PayloadField>,
+    /// Region crops, only included when `webhook_include_images` is set
+    pub images: Vec<PayloadImage>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PayloadField {
+    pub region: String,
+    pub label: Option<String>,
+    /// The manual correction when there is one, otherwise the OCR value
+    pub value: String,
+    pub number: Option<i64>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PayloadImage {
+    pub region: String,
+    pub content_type: String,
+    /// Base64 encoded image data
+    pub data: String,
+}
+
+/// Why a delivery failed and whether trying again later can help
+#[derive(Debug)]
+pub struct DeliveryError {
+    pub retry: bool,
+    pub message: String,
+}
+
+impl fmt::Display for DeliveryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for DeliveryError {}
+
+/// Webhook URLs from the `webhook_urls` setting, one per line or comma separated
+pub async fn configured_urls<R: Runtime>(app: &AppHandle<R>) -> Vec<String> {
+    db::setting(app, "webhook_urls").await.map(|value| parse_urls(&value)).unwrap_or_default()
+}
+
+fn parse_urls(value: &str) -> Vec<String> {
+    value
+        .split(['\n', ','])
+        .map(str::trim)
+        .filter(|url| !url.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Queues every match the user submits once its fields are recognized.
+/// Re-process runs only emit `match-recognized`, so old matches are not announced again.
+fn listen_for_matches(app: &AppHandle) {
+    let handle = app.clone();
+    app.listen_any(SUBMITTED_EVENT, move |event| {
+        let Ok(screenshot_id) = serde_json::from_str::<i32>(event.payload()) else {
+            warn!("Unexpected {} payload: {}", SUBMITTED_EVENT, event.payload());
+            return;
+        };
+
+        let handle = handle.clone();
+        tauri::async_runtime::spawn(async move {
+            match enqueue_match(&handle, screenshot_id).await {
+                Ok(0) => {}
+                Ok(queued) => info!("Queued screenshot {} for {} webhooks", screenshot_id, queued),
+                Err(e) => error!("Failed to queue screenshot {} for webhooks: {}", screenshot_id, e),
+            }
+        });
+    });
+}
+
+/// Writes one outbox row per configured URL so deliveries survive restarts and outages.
+/// A match with an outbox row from before, pending, delivered or failed, is skipped, so
+/// submitting or re-processing it again never posts it twice.
+pub async fn enqueue_match(app: &AppHandle, screenshot_id: i32) -> Result<usize, Box<dyn Error + Send + Sync>> {
+    let urls = configured_urls(app).await;
+    if urls.is_empty() {
+        return Ok(0);
+    }
+
+    let queued_before: i64 = db::run(app, move |conn| {
+        webhook_outbox::table
+            .filter(webhook_outbox::screenshot_id.eq(screenshot_id))
+            .count()
+            .get_result(conn)
+    })
+    .await?;
+    if queued_before > 0 {
+        return Ok(0);
+    }
+
+    let payload = serde_json::to_string(&build_payload(app, screenshot_id).await?)?;
+    let queued = urls.len();
+    db::run(app, move |conn| {
+        let now = Local::now().naive_local();
+        for url in urls {
+            diesel::insert_into(webhook_outbox::table)
+                .values(&WebhookDelivery {
+                    id: None,
+                    screenshot_id: Some(screenshot_id),
+                    url,
+                    payload: payload.clone(),
+                    status: "pending".to_string(),
+                    attempts: 0,
+                    next_attempt_at: now,
+                    last_error: None,
+                    created_at: now,
+                    delivered_at: None,
+                })
+                .execute(conn)?;
+        }
+        Ok::<_, diesel::result::Error>(())
+    })
+    .await?;
+
+    WAKE.notify_one();
+    Ok(queued)
+}
+
+async fn build_payload(app: &AppHandle, screenshot_id: i32) -> Result<MatchPayload, Box<dyn Error + Send + Sync>> {
+    let ((detected, manual, language, created_at), records) = db::run(app, move |conn| {
+        let screenshot: (String, Option<String>, Option<String>, NaiveDateTime) = screenshots::table
+            .filter(screenshots::id.eq(screenshot_id))
+            .select((screenshots::mission_type, screenshots::manual_mission_type, screenshots::language, screenshots::created_at))
+            .first(conn)?;
+        Ok::<_, diesel::result::Error>((screenshot, db::get_match_fields(conn, screenshot_id)?))
+    })
+    .await?;
+
+    let fields = records
+        .iter()
+        .map(|record| PayloadField {
+            region: record.region.clone(),
+            label: record.effective_label().map(str::to_string),
+            value: record.effective_value().to_string(),
+            number: record.effective_number(),
+        })
+        .collect();
+
+    let mut images = Vec::new();
+    if db::setting(app, "webhook_include_images").await.as_deref() == Some("true") {
+        for region in CropRegion::ALL {
+            match thumbnail::get(app, screenshot_id, Some(region)).await {
+                Ok(data) => images.push(PayloadImage {
+                    region: format!("{:?}", region),
+                    content_type: "image/jpeg".to_string(),
+                    data,
+                }),
+                Err(e) => warn!("No preview of {:?} for the webhook: {}", region, e),
+            }
+        }
+    }
+
+    Ok(MatchPayload {
+        event: "match".to_string(),
+        screenshot_id,
+        mission_type: manual.unwrap_or(detected),
+        language,
+        created_at,
+        fields,
+        images,
+    })
+}
+
+/// Starts the background task that posts queued matches and retries failed deliveries
+pub fn start_delivery(app: &AppHandle) {
+    listen_for_matches(app);
+
+    let handle = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let client = match client() {
+            Ok(client) => client,
+            Err(e) => {
+                error!("Failed to create the webhook client: {}", e);
+                return;
+            }
+        };
+
+        loop {
+            if let Err(e) = deliver_due(&handle, &client).await {
+                error!("Webhook delivery failed: {}", e);
+            }
+            tokio::select! {
+                _ = WAKE.notified() => {}
+                _ = tokio::time::sleep(POLL_INTERVAL) => {}
+            }
+        }
+    });
+}
+
+fn client() -> reqwest::Result<Client> {
+    Client::builder().timeout(REQUEST_TIMEOUT).build()
+}
+
+/// Posts every pending delivery whose retry time has come
+async fn deliver_due(app: &AppHandle, client: &Client) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let due: Vec<(i32, String, String, i32)> = db::run(app, move |conn| {
+        webhook_outbox::table
+            .filter(webhook_outbox::status.eq("pending"))
+            .filter(webhook_outbox::next_attempt_at.le(Local::now().naive_local()))
+            .select((webhook_outbox::id, webhook_outbox::url, webhook_outbox::payload, webhook_outbox::attempts))
+            .order(webhook_outbox::id.asc())
+            .load(conn)
+    })
+    .await?;
+
+    for (delivery_id, url, payload, attempts) in due {
+        let result = match serde_json::from_str::<MatchPayload>(&payload) {
+            Ok(payload) => send(client, &url, &payload).await,
+            Err(e) => Err(DeliveryError { retry: false, message: format!("Invalid stored payload: {}", e) }),
+        };
+
+        match result {
+            Ok(()) => {
+                info!("Delivered webhook {} to {}", delivery_id, url);
+                // The row stays as a record that the match was sent, only the payload with its images is dropped
+                db::run(app, move |conn| {
+                    diesel::update(webhook_outbox::table.filter(webhook_outbox::id.eq(delivery_id)))
+                        .set((
+                            webhook_outbox::status.eq("delivered"),
+                            webhook_outbox::payload.eq(""),
+                            webhook_outbox::delivered_at.eq(Some(Local::now().naive_local())),
+                        ))
+                        .execute(conn)
+                })
+                .await?;
+            }
+            Err(e) => {
+                let attempts = attempts + 1;
+                let give_up = !e.retry || attempts >= MAX_ATTEMPTS;
+                if give_up {
+                    warn!("Giving up on webhook {} to {} after {} attempts: {}", delivery_id, url, attempts, e);
+                } else {
+                    warn!("Webhook {} to {} failed, retrying in {}s: {}", delivery_id, url, backoff(attempts).num_seconds(), e);
+                }
+
+                let status = if give_up { "failed" } else { "pending" };
+                let next_attempt_at = Local::now().naive_local() + backoff(attempts);
+                db::run(app, move |conn| {
+                    diesel::update(webhook_outbox::table.filter(webhook_outbox::id.eq(delivery_id)))
+                        .set((
+                            webhook_outbox::status.eq(status),
+                            webhook_outbox::attempts.eq(attempts),
+                            webhook_outbox::next_attempt_at.eq(next_attempt_at),
+                            webhook_outbox::last_error.eq(e.message),
+                        ))
+                        .execute(conn)
+                })
+                .await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Delay before retrying a delivery that failed `attempts` times
+fn backoff(attempts: i32) -> chrono::Duration {
+    let exponent = attempts.saturating_sub(1).clamp(0, 16) as u32;
+    chrono::Duration::seconds((BASE_BACKOFF_SECS * 2_i64.pow(exponent)).min(MAX_BACKOFF_SECS))
+}
+
+/// Posts a payload to one URL. Timeouts, rate limits and server errors can be retried,
+/// other client errors mean the URL or payload is wrong and won't be.
+pub async fn send(client: &Client, url: &str, payload: &MatchPayload) -> Result<(), DeliveryError> {
+    let parsed = Url::parse(url).map_err(|e| DeliveryError { retry: false, message: format!("Invalid webhook URL {}: {}", url, e) })?;
+    let request = if is_discord(&parsed) {
+        let form = discord_form(payload).map_err(|e| DeliveryError { retry: false, message: e.to_string() })?;
+        client.post(parsed).multipart(form)
+    } else {
+        client.post(parsed).json(payload)
+    };
+
+    let response = request.send().await.map_err(|e| DeliveryError { retry: true, message: e.to_string() })?;
+    let status = response.status();
+    if status.is_success() {
+        return Ok(());
+    }
+
+    let retry = status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS || status == StatusCode::REQUEST_TIMEOUT;
+    let body = response.text().await.unwrap_or_default();
+    Err(DeliveryError { retry, message: format!("Webhook responded {}: {}", status, body.chars().take(200).collect::<String>()) })
+}
+
+/// Posts a sample match to a URL without going through the outbox
+pub async fn send_test(url: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let payload = MatchPayload {
+        event: "test".to_string(),
+        screenshot_id: 0,
+        mission_type: "bounty".to_string(),
+        language: Some("en".to_string()),
+        created_at: Local::now().naive_local(),
+        fields: vec![
+            PayloadField { region: "SummaryFirst".to_string(), label: Some("bounty_collected".to_string()), value: "2".to_string(), number: Some(2) },
+            PayloadField { region: "SummarySecond".to_string(), label: Some("hunters_killed".to_string()), value: "3".to_string(), number: Some(3) },
+        ],
+        images: Vec::new(),
+    };
+    send(&client()?, url, &payload).await?;
+    Ok(())
+}
+
+fn is_discord(url: &Url) -> bool {
+    matches!(url.host_str(), Some("discord.com" | "discordapp.com" | "ptb.discord.com" | "canary.discord.com"))
+        && url.path().starts_with("/api/webhooks/")
+}
+
+/// Discord takes a `payload_json` part with the embed and one file part per image
+fn discord_form(payload: &MatchPayload) -> Result<Form, Box<dyn Error + Send + Sync>> {
+    use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+
+    let mut form = Form::new().text("payload_json", discord_message(payload).to_string());
+    for (index, image) in payload.images.iter().enumerate() {
+        let part = Part::bytes(BASE64.decode(&image.data)?)
+            .file_name(format!("{}.jpg", image.region))
+            .mime_str(&image.content_type)?;
+        form = form.part(format!("files[{}]", index), part);
+    }
+    Ok(form)
+}
+
+fn discord_message(payload: &MatchPayload) -> serde_json::Value {
+    let title = match payload.mission_type.as_str() {
+        "bounty" => "Bounty Hunt".to_string(),
+        "soul_survival" => "Soul Survivor".to_string(),
+        other => title_case(other),
+    };
+    let fields: Vec<serde_json::Value> = payload
+        .fields
+        .iter()
+        .filter(|field| !field.value.is_empty())
+        .map(|field| {
+            let name = match &field.label {
+                Some(label) => title_case(label),
+                None if field.region == "SummaryUsername" => "Hunter".to_string(),
+                None => field.region.clone(),
+            };
+            serde_json::json!({ "name": name, "value": field.value, "inline": true })
+        })
+        .collect();
+    let timestamp = Local.from_local_datetime(&payload.created_at).single().map(|time| time.to_rfc3339());
+
+    serde_json::json!({
+        "username": DISCORD_USERNAME,
+        "embeds": [{
+            "title": title,
+            "fields": fields,
+            "timestamp": timestamp,
+        }],
+    })
+}
+
+fn title_case(value: &str) -> String {
+    value
+        .split('_')
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            chars.next().map(|first| first.to_uppercase().chain(chars).collect::<String>()).unwrap_or_default()
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    fn payload() -> MatchPayload {
+        MatchPayload {
+            event: "match".to_string(),
+            screenshot_id: 7,
+            mission_type: "bounty".to_string(),
+            language: Some("en".to_string()),
+            created_at: chrono::NaiveDate::from_ymd_opt(2025, 3, 1).unwrap().and_hms_opt(20, 15, 0).unwrap(),
+            fields: vec![PayloadField { region: "SummarySecond".to_string(), label: Some("hunters_killed".to_string()), value: "3".to_string(), number: Some(3) }],
+            images: Vec::new(),
+        }
+    }
+
+    /// Accepts one request, answers it with `status` and returns what was received
+    async fn stand_in(status: &'static str) -> (String, tokio::task::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0u8; 4096];
+            loop {
+                let read = socket.read(&mut buffer).await.unwrap();
+                request.extend_from_slice(&buffer[..read]);
+                let text = String::from_utf8_lossy(&request).to_string();
+                if let Some(end) = text.find("\r\n\r\n") {
+                    let length = text[..end]
+                        .lines()
+                        .find_map(|line| line.to_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse::<usize>().unwrap()))
+                        .unwrap_or(0);
+                    if request.len() >= end + 4 + length || read == 0 {
+                        break;
+                    }
+                }
+            }
+            socket.write_all(format!("HTTP/1.1 {}\r\ncontent-length: 0\r\nconnection: close\r\n\r\n", status).as_bytes()).await.unwrap();
+            String::from_utf8_lossy(&request).to_string()
+        });
+        (url, server)
+    }
+
+    #[tokio::test]
+    async fn test_send_to_local_stand_in() {
+        let client = client().unwrap();
+
+        let (url, server) = stand_in("204 No Content").await;
+        send(&client, &url, &payload()).await.unwrap();
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /hook"));
+        assert!(request.contains(r#""mission_type":"bounty""#));
+        assert!(request.contains(r#""label":"hunters_killed""#));
+
+        let (url, server) = stand_in("503 Service Unavailable").await;
+        assert!(send(&client, &url, &payload()).await.unwrap_err().retry);
+        server.await.unwrap();
+
+        let (url, server) = stand_in("404 Not Found").await;
+        assert!(!send(&client, &url, &payload()).await.unwrap_err().retry);
+        server.await.unwrap();
+    }
+
+    #[test]
+    fn test_backoff_and_discord_format() {
+        assert_eq!(backoff(1).num_seconds(), 30);
+        assert_eq!(backoff(3).num_seconds(), 120);
+        assert_eq!(backoff(MAX_ATTEMPTS * 4).num_seconds(), MAX_BACKOFF_SECS);
+
+        assert!(is_discord(&Url::parse("https://discord.com/api/webhooks/1/abc").unwrap()));
+        assert!(!is_discord(&Url::parse("https://example.com/api/webhooks/1/abc").unwrap()));
+
+        let message = discord_message(&payload());
+        assert_eq!(message["embeds"][0]["title"], "Bounty Hunt");
+        assert_eq!(message["embeds"][0]["fields"][0]["name"], "Hunters Killed");
+        assert_eq!(parse_urls("https://a.test/hook\n, https://b.test/hook ,"), vec!["https://a.test/hook", "https://b.test/hook"]);
+    }
+}
@@ -192,6 +192,7 @@ interface Screenshot extends BaseModel {
   recognized: boolean;
   ocr: boolean;
   created_at: string;
+  image_format: string | null;
 }
 
 export class Screenshots extends Model<Screenshot> {
@@ -0,0 +1,45 @@
+// Error returned by every backend command, see src-tauri/src/error.rs
+export type ErrorCode =
+  | 'capture_failed'
+  | 'window_not_found'
+  | 'model_missing'
+  | 'model_invalid'
+  | 'ocr_failed'
+  | 'image_failed'
+  | 'db_unavailable'
+  | 'db_locked'
+  | 'db_failed'
+  | 'not_found'
+  | 'invalid_input'
+  | 'busy'
+  | 'io'
+  | 'unauthorized'
+  | 'internal'
+
+export interface AppError {
+  code: ErrorCode
+  message: string
+}
+
+export const isAppError = (err: unknown): err is AppError => {
+  return typeof err === 'object' && err !== null && 'code' in err && 'message' in err
+}
+
+const HINTS: Partial<Record<ErrorCode, string>> = {
+  window_not_found: 'Hunt: Showdown is not running or its window could not be found.',
+  capture_failed: 'The game window could not be captured. Try running the game in borderless mode.',
+  model_missing: 'An OCR model is missing. Reinstall the app or check the model directory setting.',
+  model_invalid: 'An OCR model is damaged. Reinstall the app or replace the file in the model directory.',
+  db_locked: 'The database is busy, please try again.',
+}
+
+// Turns anything thrown by invoke into a message for the user
+export const errorMessage = (err: unknown, fallback: string): string => {
+  if (isAppError(err)) {
+    return HINTS[err.code] ?? err.message
+  }
+  if (err instanceof Error) {
+    return err.message
+  }
+  return typeof err === 'string' ? err : fallback
+}
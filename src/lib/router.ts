@@ -16,6 +16,12 @@ const routes = [
     meta: { layout: AppLayout },
     component: () => import('../views/SettingsView.vue')
   },
+  { 
+    path: '/logs', 
+    name: 'logs', 
+    meta: { layout: AppLayout },
+    component: () => import('../views/LogsView.vue')
+  },
   { 
     path: '/screenshot', 
     name: 'screenshot', 